@@ -1,4 +1,5 @@
 use std::error::Error;
+use std::fmt;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 fn main() -> Result<(), Box<dyn Error>> {
@@ -44,25 +45,69 @@ enum Instruction {
     F(i64),
     L(i64),
     R(i64),
+    // set the waypoint to an offset relative to the ship, e.g. `P10,-1`
+    P(i64, i64),
+    // move the ship to an absolute position, e.g. `T0,0`
+    T(i64, i64),
+}
+
+#[derive(Debug, Clone)]
+struct StrError(String);
+impl Error for StrError {}
+impl fmt::Display for StrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
 }
 
 impl Instruction {
     fn parse(s: &str) -> Result<Self, Box<dyn Error>> {
-        let (op, arg) = s.split_at(1);
+        if s.is_empty() {
+            return Err(Box::new(StrError("empty instruction".into())));
+        }
+        let (op, arg) = s.split_at(s.chars().next().unwrap().len_utf8());
         let instruction = match op {
             "N" => Self::N(arg.parse()?),
             "E" => Self::E(arg.parse()?),
             "S" => Self::S(arg.parse()?),
             "W" => Self::W(arg.parse()?),
             "F" => Self::F(arg.parse()?),
-            "L" => Self::L(arg.parse()?),
-            "R" => Self::R(arg.parse()?),
-            _ => panic!("Unknown op"),
+            "L" => Self::L(parse_angle(arg)?),
+            "R" => Self::R(parse_angle(arg)?),
+            "P" => {
+                let (x, y) = parse_pair(arg)?;
+                Self::P(x, y)
+            }
+            "T" => {
+                let (x, y) = parse_pair(arg)?;
+                Self::T(x, y)
+            }
+            _ => return Err(Box::new(StrError(format!("unknown op in {:?}", s)))),
         };
         Ok(instruction)
     }
 }
 
+// only right angles are supported, negative angles turn the other way
+fn parse_angle(s: &str) -> Result<i64, Box<dyn Error>> {
+    let deg: i64 = s.parse()?;
+    if deg % 90 != 0 {
+        return Err(Box::new(StrError(format!(
+            "rotation must be a multiple of 90 degrees, got {}",
+            deg
+        ))));
+    }
+    Ok(deg)
+}
+
+fn parse_pair(s: &str) -> Result<(i64, i64), Box<dyn Error>> {
+    let mut iter = s.split(',');
+    match (iter.next(), iter.next(), iter.next()) {
+        (Some(x), Some(y), None) => Ok((x.trim().parse()?, y.trim().parse()?)),
+        _ => Err(Box::new(StrError(format!("expected `x,y`, got {:?}", s)))),
+    }
+}
+
 struct Vm {
     program: Program,
     instruction_pointer: usize,
@@ -74,7 +119,7 @@ struct Vm {
 
 impl Vm {
     fn run(&mut self) -> i64 {
-        while &self.instruction_pointer < &self.program.instructions.len() {
+        while self.instruction_pointer < self.program.instructions.len() {
             let loaded_instruction = &self.program.instructions[self.instruction_pointer].clone();
             self.exec(loaded_instruction);
             dbg!(self.x, self.y, self.wx, self.wy);
@@ -97,24 +142,18 @@ impl Vm {
                 self.wx -= arg;
             }
             Instruction::L(arg) => {
-                let mut deg = arg;
-                while deg > 0 {
-                    let temp = self.wx;
-                    self.wx = self.wy;
-                    self.wy = temp;
-                    self.wy *= -1;
-                    deg -= 90;
-                }
+                self.rotate(-arg);
             }
             Instruction::R(arg) => {
-                let mut deg = arg;
-                while deg > 0 {
-                    let temp = self.wx;
-                    self.wx = self.wy;
-                    self.wy = temp;
-                    self.wx *= -1;
-                    deg -= 90;
-                }
+                self.rotate(arg);
+            }
+            Instruction::P(x, y) => {
+                self.wx = x;
+                self.wy = y;
+            }
+            Instruction::T(x, y) => {
+                self.x = x;
+                self.y = y;
             }
             Instruction::F(arg) => {
                 self.x += arg * self.wx;
@@ -123,4 +162,70 @@ impl Vm {
         }
         self.instruction_pointer += 1;
     }
+
+    // clockwise, y grows southwards
+    fn rotate(&mut self, deg: i64) {
+        for _ in 0..deg.rem_euclid(360) / 90 {
+            std::mem::swap(&mut self.wx, &mut self.wy);
+            self.wx *= -1;
+        }
+    }
+}
+
+#[cfg(test)]
+fn test_vm(instructions: &[&str]) -> Vm {
+    let instructions = instructions
+        .iter()
+        .map(|s| Instruction::parse(s).unwrap())
+        .collect();
+    Vm {
+        program: Program { instructions },
+        x: 0,
+        y: 0,
+        wx: 10,
+        wy: -1,
+        instruction_pointer: 0,
+    }
+}
+
+#[test]
+fn parse_angle_rejects_non_right_angles() {
+    assert!(Instruction::parse("L45").is_err());
+    assert!(Instruction::parse("R-30").is_err());
+    assert!(parse_angle("45").is_err());
+    assert_eq!(parse_angle("-270").unwrap(), -270);
+}
+
+#[test]
+fn rotations_agree() {
+    let waypoint = |instruction: &str| {
+        let mut vm = test_vm(&[instruction]);
+        vm.run();
+        (vm.wx, vm.wy)
+    };
+    assert_eq!(waypoint("L90"), (-1, -10));
+    assert_eq!(waypoint("L90"), waypoint("R270"));
+    assert_eq!(waypoint("L90"), waypoint("R-90"));
+    assert_eq!(waypoint("R360"), (10, -1));
+
+    let mut vm = test_vm(&[]);
+    vm.rotate(-90);
+    assert_eq!((vm.wx, vm.wy), (-1, -10));
+}
+
+#[test]
+fn parse_rejects_malformed() {
+    assert!(Instruction::parse("").is_err());
+    assert!(Instruction::parse("X10").is_err());
+    assert!(Instruction::parse("P1").is_err());
+    assert!(Instruction::parse("T1,2,3").is_err());
+    assert!(Instruction::parse("Fx").is_err());
+}
+
+#[test]
+fn set_waypoint_and_position() {
+    let mut vm = test_vm(&["P3,-4", "T100,200", "F2"]);
+    assert_eq!(vm.run(), 106 + 192);
+    assert_eq!((vm.wx, vm.wy), (3, -4));
+    assert_eq!((vm.x, vm.y), (106, 192));
 }
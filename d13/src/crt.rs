@@ -0,0 +1,148 @@
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{One, Signed, Zero};
+use std::error::Error;
+use std::fmt;
+
+/// A congruence `x ≡ remainder (mod modulus)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Congruence {
+    pub remainder: BigInt,
+    pub modulus: BigInt,
+}
+
+impl Congruence {
+    /// Builds a congruence, normalising the remainder into `0..modulus`.
+    pub fn new(remainder: impl Into<BigInt>, modulus: impl Into<BigInt>) -> Result<Self, CrtError> {
+        let modulus = modulus.into();
+        if !modulus.is_positive() {
+            return Err(CrtError::InvalidModulus(modulus));
+        }
+        let remainder = remainder.into().mod_floor(&modulus);
+        Ok(Congruence { remainder, modulus })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum CrtError {
+    /// A modulus was zero or negative.
+    InvalidModulus(BigInt),
+    /// Two congruences can't hold at the same time, e.g. `x ≡ 0 (mod 4)` and `x ≡ 1 (mod 6)`.
+    Inconsistent(Box<Congruence>, Box<Congruence>),
+}
+
+impl Error for CrtError {}
+impl fmt::Display for CrtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CrtError::InvalidModulus(n) => write!(f, "modulus must be positive, got {}", n),
+            CrtError::Inconsistent(a, b) => write!(
+                f,
+                "x ≡ {} (mod {}) and x ≡ {} (mod {}) have no common solution",
+                a.remainder, a.modulus, b.remainder, b.modulus
+            ),
+        }
+    }
+}
+
+/// Returns `(g, s, t)` such that `g = gcd(a, b) = s * a + t * b`.
+pub fn extended_gcd(a: &BigInt, b: &BigInt) -> (BigInt, BigInt, BigInt) {
+    //from wiki
+    let mut old_r = a.clone();
+    let mut r = b.clone();
+    let mut old_s = BigInt::one();
+    let mut s = BigInt::zero();
+    let mut old_t = BigInt::zero();
+    let mut t = BigInt::one();
+
+    while !r.is_zero() {
+        let quotient = &old_r / &r;
+        let next_r = &old_r - &quotient * &r;
+        old_r = std::mem::replace(&mut r, next_r);
+        let next_s = &old_s - &quotient * &s;
+        old_s = std::mem::replace(&mut s, next_s);
+        let next_t = &old_t - &quotient * &t;
+        old_t = std::mem::replace(&mut t, next_t);
+    }
+    if old_r.is_negative() {
+        (-old_r, -old_s, -old_t)
+    } else {
+        (old_r, old_s, old_t)
+    }
+}
+
+/// The inverse of `a` modulo `m`, if `a` and `m` are coprime.
+pub fn mod_inverse(a: &BigInt, m: &BigInt) -> Option<BigInt> {
+    let (g, s, _) = extended_gcd(a, m);
+    if g.is_one() {
+        Some(s.mod_floor(m))
+    } else {
+        None
+    }
+}
+
+/// Merges two congruences into one whose modulus is the lcm of theirs.
+pub fn combine(a: &Congruence, b: &Congruence) -> Result<Congruence, CrtError> {
+    let g = a.modulus.gcd(&b.modulus);
+    let diff = &b.remainder - &a.remainder;
+    if !(&diff % &g).is_zero() {
+        return Err(CrtError::Inconsistent(
+            Box::new(a.clone()),
+            Box::new(b.clone()),
+        ));
+    }
+    // x = a.remainder + k * a.modulus, solve for k modulo b.modulus / g
+    let n = &b.modulus / &g;
+    let inverse =
+        mod_inverse(&(&a.modulus / &g), &n).expect("moduli divided by their gcd are coprime");
+    let k = (&diff / &g * inverse).mod_floor(&n);
+    let x = &a.remainder + k * &a.modulus;
+    Congruence::new(x, &a.modulus * n)
+}
+
+/// Solves a system of congruences, the moduli don't need to be pairwise coprime.
+///
+/// The solution is unique modulo the returned congruence's modulus. An empty system is
+/// satisfied by everything, i.e. `x ≡ 0 (mod 1)`.
+pub fn solve<I>(congruences: I) -> Result<Congruence, CrtError>
+where
+    I: IntoIterator<Item = Congruence>,
+{
+    congruences
+        .into_iter()
+        .try_fold(Congruence::new(0, 1)?, |acc, c| {
+            // fields are public, so a congruence may not have come from `new`
+            if !c.modulus.is_positive() {
+                return Err(CrtError::InvalidModulus(c.modulus));
+            }
+            combine(&acc, &c)
+        })
+}
+
+#[test]
+fn non_coprime_moduli() {
+    let system = vec![
+        Congruence::new(0, 4).unwrap(),
+        Congruence::new(2, 6).unwrap(),
+    ];
+    assert_eq!(solve(system).unwrap(), Congruence::new(8, 12).unwrap());
+}
+
+#[test]
+fn inconsistent_system() {
+    let system = vec![
+        Congruence::new(0, 4).unwrap(),
+        Congruence::new(1, 6).unwrap(),
+    ];
+    assert!(matches!(solve(system), Err(CrtError::Inconsistent(_, _))));
+}
+
+#[test]
+fn inverse_needs_coprime() {
+    let (a, m) = (BigInt::from(3), BigInt::from(7));
+    assert_eq!(mod_inverse(&a, &m), Some(BigInt::from(5)));
+    assert_eq!(mod_inverse(&BigInt::from(4), &BigInt::from(6)), None);
+    let (g, s, t) = extended_gcd(&BigInt::from(4), &BigInt::from(6));
+    assert_eq!(g, BigInt::from(2));
+    assert_eq!(s * 4 + t * 6, g);
+}
@@ -0,0 +1,86 @@
+use d13::crt::{self, Congruence, CrtError};
+use num_bigint::BigInt;
+use num_integer::Integer;
+use std::error::Error;
+use std::fmt::Write;
+use std::io::BufRead;
+
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    /// Earliest timestamp we could depart at.
+    pub earliest: u64,
+    /// `(offset, bus id)` for every bus in service, the offset being its position in the list.
+    pub buses: Vec<(u64, u64)>,
+}
+
+impl Schedule {
+    pub fn read<R: BufRead>(mut reader: R) -> Result<Self, Box<dyn Error>> {
+        let mut buf = String::new();
+        reader.read_line(&mut buf)?;
+        let earliest = buf.trim().parse()?;
+        buf.clear();
+        reader.read_line(&mut buf)?;
+        let buses: Result<Vec<(u64, u64)>, Box<dyn Error>> = buf
+            .split(',')
+            .enumerate()
+            .filter(|(_, x)| x.trim() != "x")
+            .map(|(i, x)| match x.trim().parse()? {
+                // a bus every 0 minutes would never leave
+                0 => Err(format!("bus id must be positive, got {:?}", x.trim()).into()),
+                bus => Ok((i as u64, bus)),
+            })
+            .collect();
+        Ok(Schedule {
+            earliest,
+            buses: buses?,
+        })
+    }
+
+    /// First bus leaving at or after `earliest`, as `(bus id, departure)`.
+    pub fn earliest_departure(&self) -> Option<(u64, u64)> {
+        self.buses
+            .iter()
+            .map(|&(_, bus)| (bus, next_departure(bus, self.earliest)))
+            .min_by_key(|&(_, departure)| departure)
+    }
+
+    /// Earliest timestamp `t` at which every bus leaves at `t + offset`.
+    pub fn alignment(&self) -> Result<BigInt, CrtError> {
+        let congruences = self
+            .buses
+            .iter()
+            // the bus leaving i minutes after t means t ≡ -i (mod bus)
+            .map(|&(i, bus)| Congruence::new(-BigInt::from(i), bus))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(crt::solve(congruences)?.remainder)
+    }
+
+    /// Next time at or after `from` at which buses `a` and `b` leave together.
+    pub fn coincide(a: u64, b: u64, from: u64) -> Result<BigInt, CrtError> {
+        let period = crt::solve(vec![Congruence::new(0, a)?, Congruence::new(0, b)?])?.modulus;
+        Ok(BigInt::from(from).div_ceil(&period) * period)
+    }
+
+    /// The puzzle's departure table for `around - radius..=around + radius`.
+    pub fn timetable(&self, around: u64, radius: u64) -> String {
+        let mut out = String::new();
+        write!(out, "{:>16}", "time").unwrap();
+        for (_, bus) in &self.buses {
+            write!(out, "  {:>8}", format!("bus {}", bus)).unwrap();
+        }
+        out.push('\n');
+        for t in around.saturating_sub(radius)..=around.saturating_add(radius) {
+            write!(out, "{:>16}", t).unwrap();
+            for (_, bus) in &self.buses {
+                let c = if t % bus == 0 { 'D' } else { '.' };
+                write!(out, "  {:>8}", c).unwrap();
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+fn next_departure(bus: u64, from: u64) -> u64 {
+    from.div_ceil(bus) * bus
+}
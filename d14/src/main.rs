@@ -1,20 +1,64 @@
-use bitvec::prelude::*;
-use std::collections::{HashMap, HashSet};
 use std::error::Error;
+use std::fmt;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::str::FromStr;
+mod memory;
+use memory::{Memory, Pattern, ADDRESS_BITS, ADDRESS_MASK};
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let file = std::env::args().nth(1).unwrap();
-    let program = Program::load(&file)?;
-    let mut vm = Vm {
-        program,
-        instruction_pointer: 0,
-        mask: Mask::default(),
-        memory: HashMap::new(),
+    let mut args = std::env::args().skip(1);
+    let file = args.next().unwrap();
+    let version = match args.next().as_deref() {
+        Some("v1") => Version::V1,
+        _ => Version::V2,
     };
+    let program = Program::load(&file)?;
+    let mut vm = Vm::new(program.clone(), version);
     let _ = dbg!(vm.run());
+    match args.next().as_deref() {
+        Some("hex") => {
+            for cell in vm.memory.cells() {
+                println!("{:09x} = {:09x}", cell.addr, cell.value);
+            }
+        }
+        Some("bin") => {
+            for cell in vm.memory.cells() {
+                println!("{:036b} = {:036b}", cell.addr, cell.value);
+            }
+        }
+        Some("log") => {
+            let cells = match args.next() {
+                Some(addr) => vm.memory.get(addr.parse()?).into_iter().collect(),
+                None => vm.memory.cells(),
+            };
+            for cell in cells {
+                let instruction = &vm.program.instructions[cell.written_by];
+                println!("{} <- #{} {:?}", cell.addr, cell.written_by, instruction);
+            }
+        }
+        Some("mask") => println!("{}", vm.mask.effective(version)),
+        Some("diff") => {
+            let other_version = match version {
+                Version::V1 => Version::V2,
+                Version::V2 => Version::V1,
+            };
+            let mut other = Vm::new(program, other_version);
+            let _ = other.run();
+            for (pattern, left, right) in vm.memory.diff(&other.memory) {
+                println!(
+                    "{} ({} addresses): {:?} {:?} {:?} {:?}",
+                    pattern,
+                    pattern.len(),
+                    version,
+                    left,
+                    other_version,
+                    right
+                );
+            }
+        }
+        _ => (),
+    }
     Ok(())
 }
 
@@ -43,56 +87,80 @@ enum Instruction {
     Mem(u64, u64),
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Version {
+    // the mask overwrites bits of the value
+    V1,
+    // the mask overwrites bits of the address, `X`s float
+    V2,
+}
+
 #[derive(Clone, Debug, Default)]
 struct Mask {
     ones: u64,
-    floating: BitVec<Lsb0, u64>,
+    zeros: u64,
+    floating: u64,
 }
 
 impl FromStr for Mask {
     type Err = ();
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut ones = bitvec![Lsb0, u64;0;64];
-        let mut floating = bitvec![Lsb0, u64;0;64];
-        let mut chars: Vec<char> = s.chars().collect();
-        chars.reverse();
-        for (i, c) in chars.into_iter().enumerate() {
+        if s.len() != ADDRESS_BITS as usize {
+            return Err(());
+        }
+        let mut mask = Mask::default();
+        for (i, c) in s.chars().rev().enumerate() {
+            let bit = 1 << i;
             match c {
-                '1' => ones.as_mut_bitslice().set(i, true),
-                'X' => floating.as_mut_bitslice().set(i, true),
-                _ => (),
+                '1' => mask.ones |= bit,
+                '0' => mask.zeros |= bit,
+                'X' => mask.floating |= bit,
+                _ => return Err(()),
             }
         }
-        let (_, ones, _) = ones.domain().region().unwrap();
-        let ones = ones[0];
-        Ok(Self { ones, floating })
+        Ok(mask)
     }
 }
+impl fmt::Display for Mask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for i in (0..ADDRESS_BITS).rev() {
+            let bit = 1 << i;
+            let c = if self.floating & bit != 0 {
+                'X'
+            } else if self.ones & bit != 0 {
+                '1'
+            } else {
+                '0'
+            };
+            write!(f, "{}", c)?;
+        }
+        Ok(())
+    }
+}
+
 impl Mask {
-    fn apply(&self, val: u64) -> Box<dyn Iterator<Item = u64>> {
-        let mut masked = val;
-        masked |= self.ones;
-        let mut permutations: HashSet<u64> = HashSet::new();
-        permutations.insert(masked);
-        for (i, _) in self
-            .floating
-            .iter()
-            .enumerate()
-            .filter(|(_, b)| **b == true)
-        {
-            let mut new_permutations: HashSet<u64> = HashSet::new();
-            for p in &permutations {
-                let mut new_p = bitarr![Lsb0, u64;0;64];
-                new_p.store(*p);
-                let v = !new_p.get(i).unwrap();
-                new_p.set(i, v);
-                let (_, new_p, _) = new_p.domain().region().unwrap();
-                let new_p = new_p[0];
-                new_permutations.insert(new_p);
-            }
-            permutations = permutations.union(&new_permutations).cloned().collect();
+    // the same mask with the bits it doesn't mention, as in the default mask, spelled out as
+    // whatever leaves them alone under `version`
+    fn effective(&self, version: Version) -> Mask {
+        let unset = ADDRESS_MASK & !(self.ones | self.zeros | self.floating);
+        match version {
+            Version::V1 => Mask {
+                floating: self.floating | unset,
+                ..self.clone()
+            },
+            Version::V2 => Mask {
+                zeros: self.zeros | unset,
+                ..self.clone()
+            },
         }
-        Box::new(permutations.into_iter())
+    }
+
+    fn apply_value(&self, val: u64) -> u64 {
+        (val | self.ones) & !self.zeros
+    }
+
+    fn apply_address(&self, addr: u64) -> Pattern {
+        Pattern::new((addr & ADDRESS_MASK) | self.ones, self.floating)
     }
 }
 
@@ -103,7 +171,7 @@ impl Instruction {
         symbols.next();
         let arg = symbols.next().unwrap();
         let instruction = match op {
-            "mask" => Self::Mask(Mask::from_str(arg).unwrap()),
+            "mask" => Self::Mask(Mask::from_str(arg).map_err(|_| format!("bad mask {:?}", arg))?),
             //if not a mask, it's a mem
             _ => {
                 let addr: u64 = op[4..op.len() - 1].parse()?;
@@ -118,32 +186,50 @@ impl Instruction {
 struct Vm {
     program: Program,
     instruction_pointer: usize,
+    version: Version,
     mask: Mask,
-    memory: HashMap<u64, u64>,
+    memory: Memory,
 }
 
 impl Vm {
-    fn run(&mut self) -> Result<u64, u64> {
-        while &self.instruction_pointer < &self.program.instructions.len() {
+    fn new(program: Program, version: Version) -> Self {
+        Vm {
+            program,
+            version,
+            instruction_pointer: 0,
+            mask: Mask::default(),
+            memory: Memory::default(),
+        }
+    }
+
+    fn run(&mut self) -> Result<u128, u128> {
+        while self.instruction_pointer < self.program.instructions.len() {
             let loaded_instruction = &self.program.instructions[self.instruction_pointer].clone();
             self.exec(loaded_instruction);
             self.instruction_pointer += 1;
         }
         if self.instruction_pointer == self.program.instructions.len() {
-            Ok(self.memory.values().sum())
+            Ok(self.memory.sum())
         } else {
-            Err(self.memory.values().sum())
+            Err(self.memory.sum())
         }
     }
 
     fn exec(&mut self, instruction: &Instruction) {
         match instruction {
             Instruction::Mask(mask) => self.mask = mask.clone(),
-            Instruction::Mem(addr, val) => {
-                for masked_addr in self.mask.apply(*addr) {
-                    self.memory.insert(masked_addr, *val);
-                }
-            }
+            Instruction::Mem(addr, val) => match self.version {
+                Version::V1 => self.memory.write(
+                    Pattern::new(*addr, 0),
+                    self.mask.apply_value(*val),
+                    self.instruction_pointer,
+                ),
+                Version::V2 => self.memory.write(
+                    self.mask.apply_address(*addr),
+                    *val,
+                    self.instruction_pointer,
+                ),
+            },
         }
     }
 }
@@ -0,0 +1,214 @@
+use std::fmt;
+
+/// Addresses are 36 bits wide.
+pub const ADDRESS_BITS: u32 = 36;
+pub const ADDRESS_MASK: u64 = (1 << ADDRESS_BITS) - 1;
+
+/// A set of addresses: bits set in `floating` take both values, the others are taken from
+/// `fixed`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pattern {
+    pub fixed: u64,
+    pub floating: u64,
+}
+
+impl Pattern {
+    pub fn new(fixed: u64, floating: u64) -> Self {
+        let floating = floating & ADDRESS_MASK;
+        Pattern {
+            fixed: fixed & ADDRESS_MASK & !floating,
+            floating,
+        }
+    }
+
+    /// Number of addresses matched.
+    pub fn len(&self) -> u64 {
+        1 << self.floating.count_ones()
+    }
+
+    pub fn overlaps(&self, other: &Pattern) -> bool {
+        let both_fixed = !self.floating & !other.floating;
+        (self.fixed ^ other.fixed) & both_fixed == 0
+    }
+
+    /// Addresses matched by both, if any.
+    pub fn intersect(&self, other: &Pattern) -> Option<Pattern> {
+        if self.overlaps(other) {
+            Some(Pattern::new(
+                self.fixed | other.fixed,
+                self.floating & other.floating,
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Splits `self` minus `other` into non-overlapping patterns, at most one per bit that
+    /// floats in `self` but not in `other`.
+    pub fn subtract(&self, other: &Pattern) -> Vec<Pattern> {
+        if !self.overlaps(other) {
+            return vec![*self];
+        }
+        let mut rest = *self;
+        let mut pieces = vec![];
+        let mut bits = self.floating & !other.floating;
+        while bits != 0 {
+            let bit = bits & bits.wrapping_neg();
+            bits &= !bit;
+            // the half of `rest` that disagrees with `other` on this bit...
+            pieces.push(Pattern::new(
+                rest.fixed | (!other.fixed & bit),
+                rest.floating & !bit,
+            ));
+            // ...and the half that agrees, which we keep narrowing down
+            rest = Pattern::new(rest.fixed | (other.fixed & bit), rest.floating & !bit);
+        }
+        pieces
+    }
+
+    /// Every address matched, lowest first.
+    pub fn addresses(&self) -> impl Iterator<Item = u64> {
+        let Pattern { fixed, floating } = *self;
+        // enumerate subsets of `floating` in increasing order
+        let mut next = Some(0u64);
+        std::iter::from_fn(move || {
+            let subset = next?;
+            next = if subset == floating {
+                None
+            } else {
+                Some((subset | !floating).wrapping_add(1) & floating)
+            };
+            Some(fixed | subset)
+        })
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for bit in (0..ADDRESS_BITS).rev() {
+            let c = if self.floating >> bit & 1 == 1 {
+                'X'
+            } else if self.fixed >> bit & 1 == 1 {
+                '1'
+            } else {
+                '0'
+            };
+            write!(f, "{}", c)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Entry {
+    pattern: Pattern,
+    value: u64,
+    // index of the instruction that wrote it
+    written_by: usize,
+}
+
+/// A single memory cell, as seen in a dump.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cell {
+    pub addr: u64,
+    pub value: u64,
+    pub written_by: usize,
+}
+
+/// Sparse memory stored as disjoint address patterns, so a write to 2^30 addresses is a
+/// single entry.
+#[derive(Clone, Debug, Default)]
+pub struct Memory {
+    entries: Vec<Entry>,
+}
+
+impl Memory {
+    pub fn write(&mut self, pattern: Pattern, value: u64, written_by: usize) {
+        let mut entries = Vec::with_capacity(self.entries.len() + 1);
+        for entry in self.entries.drain(..) {
+            entries.extend(
+                entry
+                    .pattern
+                    .subtract(&pattern)
+                    .into_iter()
+                    .map(|pattern| Entry { pattern, ..entry }),
+            );
+        }
+        entries.push(Entry {
+            pattern,
+            value,
+            written_by,
+        });
+        self.entries = entries;
+    }
+
+    /// Can't overflow: at most 2^36 addresses each holding less than 2^64.
+    pub fn sum(&self) -> u128 {
+        self.entries
+            .iter()
+            .map(|e| e.pattern.len() as u128 * e.value as u128)
+            .sum()
+    }
+
+    pub fn get(&self, addr: u64) -> Option<Cell> {
+        let target = Pattern::new(addr, 0);
+        self.entries
+            .iter()
+            .find(|e| e.pattern.overlaps(&target))
+            .map(|e| Cell {
+                addr,
+                value: e.value,
+                written_by: e.written_by,
+            })
+    }
+
+    /// Every written cell sorted by address. Expands floating writes, so this can be huge.
+    pub fn cells(&self) -> Vec<Cell> {
+        let mut cells: Vec<Cell> = self
+            .entries
+            .iter()
+            .flat_map(|e| {
+                e.pattern.addresses().map(move |addr| Cell {
+                    addr,
+                    value: e.value,
+                    written_by: e.written_by,
+                })
+            })
+            .collect();
+        cells.sort_unstable_by_key(|c| c.addr);
+        cells
+    }
+
+    /// Disjoint patterns of addresses whose contents differ, with the value on each side if
+    /// any, ordered by their lowest address. Works on the stored patterns, so floating
+    /// writes are never expanded.
+    pub fn diff(&self, other: &Memory) -> Vec<(Pattern, Option<u64>, Option<u64>)> {
+        let mut diff = vec![];
+        for l in &self.entries {
+            for r in &other.entries {
+                if let Some(both) = l.pattern.intersect(&r.pattern) {
+                    if l.value != r.value {
+                        diff.push((both, Some(l.value), Some(r.value)));
+                    }
+                }
+            }
+        }
+        for (left, right, flip) in [(self, other, false), (other, self, true)] {
+            for entry in &left.entries {
+                // what's left of the entry once everything the other side wrote is removed
+                let only = right.entries.iter().fold(vec![entry.pattern], |pieces, e| {
+                    pieces.iter().flat_map(|p| p.subtract(&e.pattern)).collect()
+                });
+                diff.extend(only.into_iter().map(|pattern| {
+                    if flip {
+                        (pattern, None, Some(entry.value))
+                    } else {
+                        (pattern, Some(entry.value), None)
+                    }
+                }));
+            }
+        }
+        diff.sort_unstable_by_key(|(pattern, _, _)| pattern.fixed);
+        diff
+    }
+}
@@ -0,0 +1,23 @@
+use crate::Elves;
+use std::collections::{BTreeMap, HashSet};
+
+/// Turn (1-based) on which `target` is first spoken, giving up after `limit` turns.
+pub fn first_turn(elves: Elves, target: u64, limit: usize) -> Option<usize> {
+    elves.take(limit).position(|n| n == target).map(|i| i + 1)
+}
+
+/// How often each gap between repeats is spoken in the first `turns` turns, not counting
+/// the starting numbers. A gap of 0 means the previous number was new.
+pub fn gap_histogram(elves: Elves, turns: usize) -> BTreeMap<u64, usize> {
+    let skip = elves.initial.len();
+    let mut histogram = BTreeMap::new();
+    for gap in elves.take(turns).skip(skip) {
+        *histogram.entry(gap).or_insert(0) += 1;
+    }
+    histogram
+}
+
+/// Number of distinct values spoken by the end of turn `turns`.
+pub fn distinct(elves: Elves, turns: usize) -> usize {
+    elves.take(turns).collect::<HashSet<u64>>().len()
+}
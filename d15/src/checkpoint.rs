@@ -0,0 +1,84 @@
+use crate::{Elves, History};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+
+// The format is line based:
+//
+//     0,3,6           starting numbers
+//     7 6             next turn and the previous number, `-` if none yet
+//     dense 30000000  backend, with the preallocated length for `dense`
+//     0 4             one `number turn` line per history entry
+//     ...
+impl Elves {
+    /// The turn the next call to `next` will play.
+    pub fn turn(&self) -> usize {
+        self.n
+    }
+
+    pub fn save(&self, filename: &str) -> Result<(), Box<dyn Error>> {
+        let mut out = BufWriter::new(File::create(filename)?);
+        let initial: Vec<String> = self.initial.iter().map(u64::to_string).collect();
+        writeln!(out, "{}", initial.join(","))?;
+        match self.prev {
+            Some(prev) => writeln!(out, "{} {}", self.n, prev)?,
+            None => writeln!(out, "{} -", self.n)?,
+        }
+        match &self.history {
+            History::Sparse(map) => {
+                writeln!(out, "sparse")?;
+                for (num, turn) in map {
+                    writeln!(out, "{} {}", num, turn)?;
+                }
+            }
+            History::Dense(vec) => {
+                writeln!(out, "dense {}", vec.len())?;
+                for (num, turn) in vec.iter().enumerate().filter(|(_, &t)| t != 0) {
+                    writeln!(out, "{} {}", num, turn)?;
+                }
+            }
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    pub fn load(filename: &str) -> Result<Self, Box<dyn Error>> {
+        let input = File::open(filename)?;
+        let buffered = BufReader::new(input);
+        let mut lines = buffered.lines();
+        let mut next_line = || -> Result<String, Box<dyn Error>> {
+            Ok(lines.next().ok_or("truncated checkpoint")??)
+        };
+        let initial: Result<Vec<u64>, _> = next_line()?.split(',').map(str::parse).collect();
+        let state = next_line()?;
+        let mut state = state.split_whitespace();
+        let n: usize = state.next().ok_or("missing turn")?.parse()?;
+        if n == 0 {
+            return Err("checkpoint turn must be at least 1".into());
+        }
+        let prev = match state.next().ok_or("missing previous number")? {
+            "-" => None,
+            prev => Some(prev.parse()?),
+        };
+        let backend = next_line()?;
+        let mut history = match backend.split_whitespace().collect::<Vec<_>>()[..] {
+            ["sparse"] => History::Sparse(HashMap::new()),
+            ["dense", len] => History::Dense(vec![0; len.parse()?]),
+            _ => return Err(format!("unknown history backend {:?}", backend).into()),
+        };
+        for line in lines {
+            let line = line?;
+            let mut pair = line.split_whitespace();
+            let num: u64 = pair.next().ok_or("missing number")?.parse()?;
+            let turn: u64 = pair.next().ok_or("missing turn")?.parse()?;
+            history.insert(num, turn);
+        }
+        Ok(Elves {
+            n,
+            initial: initial?,
+            history,
+            prev,
+        })
+    }
+}
@@ -1,25 +1,138 @@
 use std::collections::HashMap;
 use std::error::Error;
+use std::time::Instant;
+mod analysis;
+mod checkpoint;
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let nums: Vec<u64> = std::env::args()
-        .nth(1)
-        .unwrap()
-        .split(",")
-        .map(|s| s.parse().unwrap())
-        .collect();
-    let mut elves = Elves::new(nums);
-    // let n: Vec<u64> = elves.take(2020).collect();
-    let n = elves.nth(29_999_999).unwrap();
-    dbg!(n);
+    let mut args = std::env::args().skip(1);
+    let first = args.next().unwrap();
+    if first == "resume" {
+        // resume <checkpoint> <turns>, then checkpoint again
+        let file = args.next().unwrap();
+        let turns = parse_turns(&args.next().unwrap())?;
+        let mut elves = Elves::load(&file)?;
+        let played = elves.turn() - 1;
+        // only the latest number survives in a checkpoint, so earlier turns are gone
+        if turns < played {
+            return Err(format!("checkpoint is already past turn {}", turns).into());
+        }
+        let n = if turns > played {
+            elves.nth(turns - played - 1)
+        } else {
+            elves.prev
+        };
+        dbg!(n.unwrap());
+        elves.save(&file)?;
+        return Ok(());
+    }
+    let nums: Vec<u64> = first.split(',').map(|s| s.parse().unwrap()).collect();
+    let turns = args.next().map_or(Ok(2020), |s| parse_turns(&s))?;
+    match args.next().as_deref() {
+        Some("map") => {
+            let mut elves = Elves::new(nums);
+            dbg!(elves.nth(turns - 1).unwrap());
+        }
+        Some("bench") => {
+            let start = Instant::now();
+            let n = Elves::new(nums.clone()).nth(turns - 1).unwrap();
+            println!("map: {} in {:?}", n, start.elapsed());
+            let start = Instant::now();
+            let n = Elves::with_turns(nums, turns).nth(turns - 1).unwrap();
+            println!("vec: {} in {:?}", n, start.elapsed());
+        }
+        Some("first") => {
+            let target: u64 = args.next().unwrap().parse()?;
+            let elves = Elves::with_turns(nums, turns);
+            dbg!(analysis::first_turn(elves, target, turns));
+        }
+        Some("gaps") => {
+            let elves = Elves::with_turns(nums, turns);
+            for (gap, count) in analysis::gap_histogram(elves, turns) {
+                println!("{}\t{}", gap, count);
+            }
+        }
+        Some("distinct") => {
+            let elves = Elves::with_turns(nums, turns);
+            dbg!(analysis::distinct(elves, turns));
+        }
+        Some("save") => {
+            // play `turns` turns and checkpoint
+            let file = args.next().unwrap();
+            let mut elves = Elves::with_turns(nums, turns);
+            dbg!(elves.nth(turns - 1).unwrap());
+            elves.save(&file)?;
+        }
+        _ => {
+            let mut elves = Elves::with_turns(nums, turns);
+            dbg!(elves.nth(turns - 1).unwrap());
+        }
+    }
     Ok(())
 }
 
+// turns are counted from 1, so there's no turn 0 to ask about
+fn parse_turns(s: &str) -> Result<usize, Box<dyn Error>> {
+    match s.parse()? {
+        0 => Err("turns must be at least 1".into()),
+        turns => Ok(turns),
+    }
+}
+
+// don't preallocate more than this many turns, 1GiB of u32s
+const MAX_DENSE_LEN: usize = 1 << 28;
+
+/// Last turn each number was spoken on.
+#[derive(Debug)]
+enum History {
+    Sparse(HashMap<u64, u64>),
+    // indexed by number, 0 means never spoken since turns start at 1
+    Dense(Vec<u32>),
+}
+
+impl History {
+    fn get(&self, num: u64) -> Option<u64> {
+        match self {
+            History::Sparse(map) => map.get(&num).copied(),
+            History::Dense(vec) => match vec.get(num as usize) {
+                Some(0) | None => None,
+                Some(&turn) => Some(turn as u64),
+            },
+        }
+    }
+
+    fn insert(&mut self, num: u64, turn: u64) {
+        if let History::Dense(vec) = self {
+            if (num as usize) < vec.len() && turn <= u32::MAX as u64 {
+                vec[num as usize] = turn as u32;
+                return;
+            }
+            // we've outgrown the preallocated range
+            self.make_sparse();
+        }
+        if let History::Sparse(map) = self {
+            map.insert(num, turn);
+        }
+    }
+
+    fn make_sparse(&mut self) {
+        if let History::Dense(vec) = self {
+            let map = vec
+                .iter()
+                .enumerate()
+                .filter(|(_, &turn)| turn != 0)
+                .map(|(num, &turn)| (num as u64, turn as u64))
+                .collect();
+            *self = History::Sparse(map);
+        }
+    }
+}
+
 #[derive(Debug)]
 struct Elves {
     n: usize,
     initial: Vec<u64>,
-    history: HashMap<u64, u64>,
+    history: History,
     prev: Option<u64>,
 }
 
@@ -28,10 +141,26 @@ impl Elves {
         Self {
             n: 1,
             initial,
-            history: HashMap::new(),
+            history: History::Sparse(HashMap::new()),
             prev: None,
         }
     }
+
+    /// Sized for playing `turns` turns: no number spoken can exceed the turn count (or the
+    /// largest starting number), so small enough games get a flat array.
+    fn with_turns(initial: Vec<u64>, turns: usize) -> Self {
+        let len = initial
+            .iter()
+            .map(|&n| n as usize + 1)
+            .chain(std::iter::once(turns))
+            .max()
+            .unwrap();
+        let mut elves = Self::new(initial);
+        if len <= MAX_DENSE_LEN {
+            elves.history = History::Dense(vec![0; len]);
+        }
+        elves
+    }
 }
 
 impl Iterator for Elves {
@@ -39,28 +168,25 @@ impl Iterator for Elves {
     fn next(&mut self) -> Option<u64> {
         let res = if self.n <= self.initial.len() {
             let num = self.initial[self.n - 1];
-            match self.prev {
-                Some(prev) => {
-                    self.history.insert(prev, (self.n as u64) - 1);
-                }
-                None => (),
+            if let Some(prev) = self.prev {
+                self.history.insert(prev, (self.n as u64) - 1);
             }
             self.prev = Some(num);
 
             Some(num)
         } else {
-            let prev = &self.prev.unwrap();
-            match self.history.get_mut(prev) {
+            let prev = self.prev.unwrap();
+            match self.history.get(prev) {
                 Some(timestamp) => {
                     //seen it before
-                    let age = (self.n - 1) as u64 - *timestamp;
-                    self.history.insert(*prev, (self.n as u64) - 1);
+                    let age = (self.n - 1) as u64 - timestamp;
+                    self.history.insert(prev, (self.n as u64) - 1);
                     Some(age)
                 }
                 None => {
                     //new number
                     // record it
-                    self.history.insert(*prev, (self.n as u64) - 1);
+                    self.history.insert(prev, (self.n as u64) - 1);
                     Some(0)
                 }
             }
@@ -0,0 +1,49 @@
+use crate::StrError;
+use std::collections::HashMap;
+use std::error::Error;
+
+pub type Ticket = HashMap<String, u64>;
+
+/// One header row with `fields` in order, then a row per ticket.
+pub fn to_csv(fields: &[String], tickets: &[Ticket]) -> Result<String, Box<dyn Error>> {
+    if let Some(field) = fields.iter().find(|f| f.contains(',')) {
+        return Err(StrError(format!("can't write field {:?} to csv", field)).into());
+    }
+    let mut out = fields.join(",");
+    out.push('\n');
+    for ticket in tickets {
+        let row: Vec<String> = fields.iter().map(|f| ticket[f].to_string()).collect();
+        out.push_str(&row.join(","));
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+pub fn from_csv(s: &str) -> Result<Vec<Ticket>, Box<dyn Error>> {
+    let mut lines = s.lines().filter(|line| !line.trim().is_empty());
+    let header = lines.next().ok_or_else(|| StrError("empty csv".into()))?;
+    let fields: Vec<String> = header.split(',').map(|f| f.trim().to_string()).collect();
+    lines
+        .map(|line| {
+            let values: Vec<&str> = line.split(',').collect();
+            if values.len() != fields.len() {
+                return Err(
+                    StrError(format!("expected {} values in {:?}", fields.len(), line)).into(),
+                );
+            }
+            fields
+                .iter()
+                .zip(values)
+                .map(|(f, v)| Ok((f.clone(), v.trim().parse()?)))
+                .collect()
+        })
+        .collect()
+}
+
+pub fn to_json(tickets: &[Ticket]) -> Result<String, Box<dyn Error>> {
+    Ok(serde_json::to_string_pretty(tickets)?)
+}
+
+pub fn from_json(s: &str) -> Result<Vec<Ticket>, Box<dyn Error>> {
+    Ok(serde_json::from_str(s)?)
+}
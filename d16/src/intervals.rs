@@ -0,0 +1,90 @@
+use std::ops::RangeInclusive;
+
+/// Looks up which rules accept a value by splitting the number line into segments at every
+/// range boundary, each segment knowing the rules that cover it.
+#[derive(Debug, Clone)]
+pub struct IntervalIndex {
+    // segment i is `starts[i]..starts[i + 1]`, the last one runs to u64::MAX
+    starts: Vec<u64>,
+    rules: Vec<Vec<usize>>,
+}
+
+impl IntervalIndex {
+    /// `intervals[rule]` are the ranges rule accepts.
+    pub fn new(intervals: &[Vec<RangeInclusive<u64>>]) -> Self {
+        let mut starts: Vec<u64> = vec![0];
+        for range in intervals.iter().flatten() {
+            starts.push(*range.start());
+            if let Some(after) = range.end().checked_add(1) {
+                starts.push(after);
+            }
+        }
+        starts.sort_unstable();
+        starts.dedup();
+        let rules = starts
+            .iter()
+            .map(|start| {
+                (0..intervals.len())
+                    .filter(|&r| intervals[r].iter().any(|range| range.contains(start)))
+                    .collect()
+            })
+            .collect();
+        IntervalIndex { starts, rules }
+    }
+
+    /// Rules accepting `n`, in order.
+    pub fn matching(&self, n: u64) -> &[usize] {
+        let segment = match self.starts.binary_search(&n) {
+            Ok(i) => i,
+            Err(i) => i - 1,
+        };
+        &self.rules[segment]
+    }
+
+    pub fn any(&self, n: u64) -> bool {
+        !self.matching(n).is_empty()
+    }
+}
+
+/// Sorts and merges `ranges` into disjoint, non-adjacent ranges.
+pub fn normalize(mut ranges: Vec<RangeInclusive<u64>>) -> Vec<RangeInclusive<u64>> {
+    ranges.sort_by_key(|r| *r.start());
+    let mut merged: Vec<RangeInclusive<u64>> = vec![];
+    for range in ranges.into_iter().filter(|r| !r.is_empty()) {
+        match merged.last_mut() {
+            Some(last) if range.start().saturating_sub(1) <= *last.end() => {
+                *last = *last.start()..=*last.end().max(range.end());
+            }
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// Removes every value in `holes` from `ranges`, both normalized.
+pub fn subtract(
+    ranges: &[RangeInclusive<u64>],
+    holes: &[RangeInclusive<u64>],
+) -> Vec<RangeInclusive<u64>> {
+    let mut result = vec![];
+    for range in ranges {
+        let mut start = *range.start();
+        let mut exhausted = false;
+        for hole in holes {
+            if hole.end() < &start || hole.start() > range.end() {
+                continue;
+            }
+            if hole.start() > &start {
+                result.push(start..=hole.start() - 1);
+            }
+            match hole.end().checked_add(1) {
+                Some(after) => start = after,
+                None => exhausted = true,
+            }
+        }
+        if !exhausted && start <= *range.end() {
+            result.push(start..=*range.end());
+        }
+    }
+    result
+}
@@ -1,115 +1,125 @@
-use std::collections::HashMap;
 use std::error::Error;
 use std::fmt;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::fs::{self, File};
+use std::io::BufReader;
 use std::ops::RangeInclusive;
 use std::str::FromStr;
+mod export;
+mod intervals;
+mod notes;
+mod solver;
+use export::Ticket;
+use intervals::IntervalIndex;
+use notes::Notes;
+use solver::Assignment;
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let file = std::env::args().nth(1).unwrap();
+    let mut args = std::env::args().skip(1);
+    let file = args.next().unwrap();
+    if file == "import" {
+        // import <decoded.csv|decoded.json>
+        let path = args.next().unwrap();
+        let contents = fs::read_to_string(&path)?;
+        let tickets = if path.ends_with(".csv") {
+            export::from_csv(&contents)?
+        } else {
+            export::from_json(&contents)?
+        };
+        println!("{:#?}", tickets);
+        return Ok(());
+    }
     let input = File::open(file)?;
-    let buffered = BufReader::new(input);
-    let mut lines = buffered.lines().map(Result::unwrap);
-    let rule_lines = lines.by_ref().take_while(|line| !line.trim().is_empty());
-    let rules: Result<Vec<Rule>, Box<dyn Error>> = rule_lines.map(|s| Rule::from_str(&s)).collect();
-    //line that looks like `your ticket:`
-    let rules = rules?;
-    lines.next();
-    let my_ticket: Vec<u64> = lines
-        .next()
-        .unwrap()
-        .split(",")
-        .map(|n| n.parse().unwrap())
-        .collect();
-    // blank line
-    lines.next();
-    // line that looks like `nearby ticket:`
-    lines.next();
-    let nearby_tickets: Vec<Vec<u64>> = lines
-        .map(|line| line.split(",").map(|n| n.parse().unwrap()).collect())
-        .collect();
+    let Notes {
+        rules,
+        my_ticket,
+        nearby_tickets,
+    } = Notes::read(BufReader::new(input))?;
+    let index = IntervalIndex::new(&rules.iter().map(Rule::intervals).collect::<Vec<_>>());
+    let mut error_rate = 0;
+    for (i, ticket) in nearby_tickets.iter().enumerate() {
+        let invalid: Vec<u64> = ticket.iter().copied().filter(|&n| !index.any(n)).collect();
+        if !invalid.is_empty() {
+            println!("nearby ticket {}: invalid {:?}", i, invalid);
+            error_rate += invalid.iter().sum::<u64>();
+        }
+    }
+    println!("ticket scanning error rate: {}", error_rate);
     let valid_nearby_tickets: Vec<Vec<u64>> = nearby_tickets
         .into_iter()
-        .filter(|ticket| {
-            ticket
-                .iter()
-                .all(|n| rules.iter().any(|rule| rule.valid(n)))
-        })
+        .filter(|ticket| ticket.iter().all(|&n| index.any(n)))
         .collect();
     let ticket_len = my_ticket.len();
-    assert!(valid_nearby_tickets
-        .iter()
-        .all(|ticket| ticket.len() == ticket_len));
-    let mut possibilities: Vec<(usize, Vec<&Rule>)> = (0..ticket_len)
+    let candidates: Vec<Vec<usize>> = (0..ticket_len)
         .map(|i| {
-            rules
-                .iter()
-                .filter(|rule| {
+            (0..rules.len())
+                .filter(|r| {
                     valid_nearby_tickets
                         .iter()
-                        .all(|ticket| rule.valid(&ticket[i]))
+                        .all(|ticket| index.matching(ticket[i]).contains(r))
                 })
                 .collect()
         })
-        .enumerate()
         .collect();
-    let mut known_fields: Vec<(usize, &Rule)> = vec![];
-    while !possibilities.is_empty() {
-        let mut newly_known_fields: Vec<(usize, &Rule)> = possibilities
-            .iter()
-            .filter_map(|(i, rules)| {
-                if rules.len() == 1 {
-                    Some((*i, rules[0]))
-                } else {
-                    None
-                }
-            })
-            .collect();
-        let rules_to_delete: Vec<Rule> = newly_known_fields
+    let decoder = match solver::solve(&candidates, rules.len(), 100) {
+        Assignment::Unique(decoder) => decoder,
+        Assignment::Ambiguous {
+            solutions,
+            positions,
+        } => {
+            for solution in &solutions {
+                let names: Vec<&str> = solution.iter().map(|&r| rules[r].name.as_str()).collect();
+                println!("{:?}", names);
+            }
+            let positions: Vec<String> = positions
+                .iter()
+                .map(|&i| format!("{} ({})", i, candidate_names(&rules, &candidates[i])))
+                .collect();
+            return Err(Box::new(StrError(format!(
+                "ambiguous, {} assignments differ at positions {}",
+                solutions.len(),
+                positions.join(", ")
+            ))));
+        }
+        Assignment::Unsatisfiable { positions, fields } => {
+            return Err(Box::new(StrError(format!(
+                "unsatisfiable, positions {:?} only fit {}",
+                positions,
+                candidate_names(&rules, &fields)
+            ))));
+        }
+    };
+    let decode = |ticket: &[u64]| -> Ticket {
+        ticket
             .iter()
-            .map(|(_, rule)| rule.clone())
-            .cloned()
-            .collect();
-        possibilities = possibilities
-            .into_iter()
-            .filter_map({
-                |(i, rules)| {
-                    if rules.len() >= 1 {
-                        Some((
-                            i,
-                            rules
-                                .iter()
-                                .filter(|rule| !rules_to_delete.contains(rule))
-                                .cloned()
-                                .collect(),
-                        ))
-                    } else {
-                        None
-                    }
-                }
-            })
+            .enumerate()
+            .map(|(i, &field)| (rules[decoder[i]].name.clone(), field))
+            .collect()
+    };
+    let decoded_ticket = decode(&my_ticket);
+    println!("{:#?}", decoded_ticket);
+    if let Some("export") = args.next().as_deref() {
+        // export <decoded.csv|decoded.json>, our ticket first then the valid nearby ones
+        let path = args.next().unwrap();
+        let tickets: Vec<Ticket> = std::iter::once(decoded_ticket)
+            .chain(valid_nearby_tickets.iter().map(|t| decode(t)))
             .collect();
-
-        known_fields.append(&mut newly_known_fields);
+        let contents = if path.ends_with(".csv") {
+            let fields: Vec<String> = decoder.iter().map(|&r| rules[r].name.clone()).collect();
+            export::to_csv(&fields, &tickets)?
+        } else {
+            export::to_json(&tickets)?
+        };
+        fs::write(path, contents)?;
     }
-    let decoder: HashMap<usize, Rule> = known_fields
-        .into_iter()
-        .map(|(i, rule)| (i, rule.clone()))
-        .collect();
-    let decoded_ticket: HashMap<String, u64> = my_ticket
-        .into_iter()
-        .enumerate()
-        .map(|(i, field)| (decoder.get(&i).unwrap().name.clone(), field))
-        .collect();
-    println!("{:#?}", decoded_ticket);
     Ok(())
 }
 
+// `name: 1-3 or 5- or -10 except 7`, ranges can be open on either end or a single value
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct Rule {
     name: String,
-    ranges: [RangeInclusive<u64>; 2],
+    ranges: Vec<RangeInclusive<u64>>,
+    exclusions: Vec<RangeInclusive<u64>>,
 }
 
 #[derive(Debug, Clone)]
@@ -124,26 +134,55 @@ impl fmt::Display for StrError {
 impl FromStr for Rule {
     type Err = Box<dyn Error>;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut iter = s.split(":");
+        let mut iter = s.splitn(2, ':');
         let name: String = iter.next().unwrap().into();
-        let mut ranges = iter.next().unwrap().split_whitespace();
-        let r1 = parse_range(ranges.next().unwrap());
-        ranges.next();
-        let r2 = parse_range(ranges.next().unwrap());
-        let ranges = [r1, r2];
-        Ok(Rule { name, ranges })
+        let spec = iter
+            .next()
+            .ok_or_else(|| StrError(format!("missing `:` in rule {:?}", s)))?;
+        let mut parts = spec.splitn(2, " except ");
+        let ranges = parse_ranges(parts.next().unwrap())?;
+        let exclusions = match parts.next() {
+            Some(exclusions) => parse_ranges(exclusions)?,
+            None => vec![],
+        };
+        Ok(Rule {
+            name,
+            ranges,
+            exclusions,
+        })
     }
 }
 
 impl Rule {
-    fn valid(&self, n: &u64) -> bool {
-        self.ranges.iter().any(|r| n >= r.start() && n <= r.end())
+    /// Disjoint, sorted ranges of the values this rule accepts.
+    fn intervals(&self) -> Vec<RangeInclusive<u64>> {
+        intervals::subtract(
+            &intervals::normalize(self.ranges.clone()),
+            &intervals::normalize(self.exclusions.clone()),
+        )
     }
 }
 
-fn parse_range(s: &str) -> RangeInclusive<u64> {
-    let mut iter = s.split("-");
-    let low: u64 = iter.next().unwrap().parse().unwrap();
-    let high: u64 = iter.next().unwrap().parse().unwrap();
-    low..=high
+fn candidate_names(rules: &[Rule], fields: &[usize]) -> String {
+    let names: Vec<&str> = fields.iter().map(|&r| rules[r].name.as_str()).collect();
+    names.join(", ")
+}
+
+fn parse_ranges(s: &str) -> Result<Vec<RangeInclusive<u64>>, Box<dyn Error>> {
+    s.split(" or ").map(|r| parse_range(r.trim())).collect()
+}
+
+fn parse_range(s: &str) -> Result<RangeInclusive<u64>, Box<dyn Error>> {
+    let mut iter = s.splitn(2, '-');
+    let low = iter.next().unwrap();
+    let low: u64 = if low.is_empty() { 0 } else { low.parse()? };
+    let high: u64 = match iter.next() {
+        None => low,
+        Some("") => u64::MAX,
+        Some(high) => high.parse()?,
+    };
+    if low > high {
+        return Err(Box::new(StrError(format!("empty range {:?}", s))));
+    }
+    Ok(low..=high)
 }
@@ -0,0 +1,83 @@
+use crate::{Rule, StrError};
+use std::error::Error;
+use std::io::BufRead;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Section {
+    Rules,
+    Mine,
+    Nearby,
+}
+
+/// The puzzle notes. Sections start at their header line and can come in any order, rules
+/// don't need a header when they come first. Blank lines and `#` comments are skipped.
+#[derive(Debug, Clone)]
+pub struct Notes {
+    pub rules: Vec<Rule>,
+    pub my_ticket: Vec<u64>,
+    pub nearby_tickets: Vec<Vec<u64>>,
+}
+
+impl Notes {
+    pub fn read<R: BufRead>(reader: R) -> Result<Self, Box<dyn Error>> {
+        let mut section = Section::Rules;
+        let mut rules = vec![];
+        let mut my_ticket = None;
+        let mut nearby_tickets = vec![];
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            section = match line {
+                "rules:" => Section::Rules,
+                "your ticket:" => Section::Mine,
+                "nearby tickets:" => Section::Nearby,
+                _ => {
+                    let err = |e: Box<dyn Error>| StrError(format!("line {}: {}", i + 1, e));
+                    match section {
+                        Section::Rules => rules.push(Rule::from_str(line).map_err(err)?),
+                        Section::Mine if my_ticket.is_some() => {
+                            return Err(err("more than one ticket of ours".into()).into())
+                        }
+                        Section::Mine => my_ticket = Some(parse_ticket(line).map_err(err)?),
+                        Section::Nearby => {
+                            nearby_tickets.push((i + 1, parse_ticket(line).map_err(err)?))
+                        }
+                    }
+                    continue;
+                }
+            };
+        }
+        let my_ticket: Vec<u64> =
+            my_ticket.ok_or_else(|| StrError("missing `your ticket:`".into()))?;
+        // sections come in any order, so lengths can only be checked once ours is known
+        if let Some((line, ticket)) = nearby_tickets
+            .iter()
+            .find(|(_, ticket)| ticket.len() != my_ticket.len())
+        {
+            return Err(Box::new(StrError(format!(
+                "line {}: ticket has {} values, ours has {}",
+                line,
+                ticket.len(),
+                my_ticket.len()
+            ))));
+        }
+        Ok(Notes {
+            rules,
+            my_ticket,
+            nearby_tickets: nearby_tickets
+                .into_iter()
+                .map(|(_, ticket)| ticket)
+                .collect(),
+        })
+    }
+}
+
+fn parse_ticket(s: &str) -> Result<Vec<u64>, Box<dyn Error>> {
+    Ok(s.split(',')
+        .map(|n| n.trim().parse())
+        .collect::<Result<_, _>>()?)
+}
@@ -0,0 +1,163 @@
+/// Outcome of matching ticket positions to fields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Assignment {
+    /// `fields[position]` is the only possible field index for every position.
+    Unique(Vec<usize>),
+    /// More than one assignment fits, `positions` are the ones that differ between them.
+    Ambiguous {
+        solutions: Vec<Vec<usize>>,
+        positions: Vec<usize>,
+    },
+    /// `positions` can only take the fewer `fields` between them, so no assignment exists.
+    Unsatisfiable {
+        positions: Vec<usize>,
+        fields: Vec<usize>,
+    },
+}
+
+/// Matches every position to a distinct field, `candidates[position]` being the fields it
+/// could be. At most `limit` solutions are collected when ambiguous.
+pub fn solve(candidates: &[Vec<usize>], num_fields: usize, limit: usize) -> Assignment {
+    let matching = max_matching(candidates, num_fields, &vec![false; num_fields]);
+    if let Some(unmatched) = matching.iter().position(Option::is_none) {
+        let (positions, fields) = hall_violator(candidates, &matching, unmatched);
+        return Assignment::Unsatisfiable { positions, fields };
+    }
+    let mut solutions = vec![];
+    let mut partial = vec![None; candidates.len()];
+    let mut used = vec![false; num_fields];
+    enumerate(candidates, &mut partial, &mut used, limit, &mut solutions);
+    if solutions.len() == 1 {
+        return Assignment::Unique(solutions.pop().unwrap());
+    }
+    let positions = (0..candidates.len())
+        .filter(|&i| solutions.iter().any(|s| s[i] != solutions[0][i]))
+        .collect();
+    Assignment::Ambiguous {
+        solutions,
+        positions,
+    }
+}
+
+// Kuhn's augmenting paths, `matching[position]` is the field it got
+fn max_matching(
+    candidates: &[Vec<usize>],
+    num_fields: usize,
+    taken: &[bool],
+) -> Vec<Option<usize>> {
+    let mut owner: Vec<Option<usize>> = vec![None; num_fields];
+    for position in 0..candidates.len() {
+        let mut seen = taken.to_vec();
+        augment(candidates, position, &mut owner, &mut seen);
+    }
+    let mut matching = vec![None; candidates.len()];
+    for (field, position) in owner.into_iter().enumerate() {
+        if let Some(position) = position {
+            matching[position] = Some(field);
+        }
+    }
+    matching
+}
+
+fn augment(
+    candidates: &[Vec<usize>],
+    position: usize,
+    owner: &mut [Option<usize>],
+    seen: &mut [bool],
+) -> bool {
+    for &field in &candidates[position] {
+        if seen[field] {
+            continue;
+        }
+        seen[field] = true;
+        let free = match owner[field] {
+            None => true,
+            Some(other) => augment(candidates, other, owner, seen),
+        };
+        if free {
+            owner[field] = Some(position);
+            return true;
+        }
+    }
+    false
+}
+
+// Positions reachable from an unmatched one by alternating paths have fewer candidate
+// fields between them than there are positions (Hall's theorem).
+fn hall_violator(
+    candidates: &[Vec<usize>],
+    matching: &[Option<usize>],
+    unmatched: usize,
+) -> (Vec<usize>, Vec<usize>) {
+    let mut positions = vec![unmatched];
+    let mut fields: Vec<usize> = vec![];
+    let mut stack = vec![unmatched];
+    while let Some(position) = stack.pop() {
+        for &field in &candidates[position] {
+            if fields.contains(&field) {
+                continue;
+            }
+            fields.push(field);
+            if let Some(other) = matching.iter().position(|&m| m == Some(field)) {
+                if !positions.contains(&other) {
+                    positions.push(other);
+                    stack.push(other);
+                }
+            }
+        }
+    }
+    positions.sort_unstable();
+    fields.sort_unstable();
+    (positions, fields)
+}
+
+// Backtracking over the most constrained position first, pruned by checking a complete
+// matching of the rest still exists.
+fn enumerate(
+    candidates: &[Vec<usize>],
+    partial: &mut [Option<usize>],
+    used: &mut [bool],
+    limit: usize,
+    solutions: &mut Vec<Vec<usize>>,
+) {
+    if solutions.len() >= limit {
+        return;
+    }
+    let next = (0..candidates.len())
+        .filter(|&i| partial[i].is_none())
+        .min_by_key(|&i| candidates[i].iter().filter(|&&f| !used[f]).count());
+    let position = match next {
+        Some(position) => position,
+        None => {
+            solutions.push(partial.iter().map(|f| f.unwrap()).collect());
+            return;
+        }
+    };
+    for &field in &candidates[position] {
+        if used[field] {
+            continue;
+        }
+        partial[position] = Some(field);
+        used[field] = true;
+        let remaining: Vec<Vec<usize>> = candidates
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                if partial[i].is_some() {
+                    vec![]
+                } else {
+                    c.clone()
+                }
+            })
+            .collect();
+        let feasible = max_matching(&remaining, used.len(), used)
+            .iter()
+            .enumerate()
+            .all(|(i, m)| partial[i].is_some() || m.is_some());
+        if feasible {
+            enumerate(candidates, partial, used, limit, solutions);
+        }
+        partial[position] = None;
+        used[field] = false;
+    }
+}
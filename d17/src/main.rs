@@ -1,91 +1,228 @@
 use std::collections::HashSet;
 use std::error::Error;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{BufRead, BufReader};
+mod render;
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let filename = std::env::args().nth(1).unwrap();
+    let mut args = std::env::args().skip(1);
+    let filename = args.next().unwrap();
+    let dimensions: usize = args.next().map_or(Ok(4), |s| s.parse())?;
+    let cycles: usize = args.next().map_or(Ok(6), |s| s.parse())?;
+    let output = match args.next().as_deref() {
+        Some("render") => Output::Render,
+        Some("bbox") => Output::BoundingBox,
+        Some("ply") => Output::Ply(args.next().unwrap()),
+        Some("obj") => Output::Obj(args.next().unwrap()),
+        _ => Output::Count,
+    };
     let input = File::open(filename)?;
     let buffered = BufReader::new(input);
     let v: Vec<Vec<u8>> = buffered.lines().map(|r| r.unwrap().into_bytes()).collect();
-    let space = Space::seed(v)
-        .next_cycle()
-        .next_cycle()
-        .next_cycle()
-        .next_cycle()
-        .next_cycle()
-        .next_cycle();
-    dbg!(space.cubes.len());
+    let active = match dimensions {
+        2 => simulate::<2>(v, cycles, &output)?,
+        3 => simulate::<3>(v, cycles, &output)?,
+        4 => simulate::<4>(v, cycles, &output)?,
+        5 => simulate::<5>(v, cycles, &output)?,
+        6 => simulate::<6>(v, cycles, &output)?,
+        7 => simulate::<7>(v, cycles, &output)?,
+        8 => simulate::<8>(v, cycles, &output)?,
+        _ => return Err(format!("unsupported dimension count {}", dimensions).into()),
+    };
+    dbg!(active);
     Ok(())
 }
 
-struct Space {
-    cubes: HashSet<(i64, i64, i64, i64)>,
+/// What to show of the space after every cycle, besides the final count.
+enum Output {
+    Count,
+    Render,
+    BoundingBox,
+    // file name prefix, `-cycleN.ply` gets appended
+    Ply(String),
+    Obj(String),
 }
 
-impl Space {
+fn simulate<const D: usize>(
+    v: Vec<Vec<u8>>,
+    cycles: usize,
+    output: &Output,
+) -> Result<usize, Box<dyn Error>> {
+    let mut space = Space::<D>::seed(v);
+    for cycle in 0..=cycles {
+        if cycle > 0 {
+            space = space.next_cycle();
+        }
+        match output {
+            Output::Count => continue,
+            Output::Render => {
+                if cycle == 0 {
+                    println!("Before any cycles:\n");
+                } else {
+                    println!(
+                        "After {} cycle{}:\n",
+                        cycle,
+                        if cycle == 1 { "" } else { "s" }
+                    );
+                }
+                print!("{}", render::slices(&space.expand()));
+            }
+            Output::BoundingBox => {
+                if let Some((min, max)) = render::bounding_box(&space.expand()) {
+                    println!("cycle {}: {:?} to {:?}", cycle, min, max);
+                }
+            }
+            Output::Ply(prefix) => {
+                let filename = format!("{}-cycle{}.ply", prefix, cycle);
+                fs::write(filename, render::ply(&space.expand()))?;
+            }
+            Output::Obj(prefix) => {
+                let filename = format!("{}-cycle{}.obj", prefix, cycle);
+                fs::write(filename, render::obj(&space.expand()))?;
+            }
+        }
+    }
+    Ok(space.len())
+}
+
+pub type Point<const D: usize> = [i64; D];
+
+/// Active cubes in `D` dimensions, seeded from a 2D slice.
+///
+/// Every dimension past the first two starts out flat at 0, so the space stays symmetric
+/// under flipping the sign of any of them and under swapping them around. Only one cube per
+/// symmetry class is stored: the one whose extra coordinates are non-negative and sorted.
+struct Space<const D: usize> {
+    cubes: HashSet<Point<D>>,
+}
+
+impl<const D: usize> Space<D> {
     fn seed(initial_state: Vec<Vec<u8>>) -> Self {
+        assert!(D >= 2, "the seed is a 2D slice");
         let mut new_space = Space {
             cubes: HashSet::new(),
         };
-        for i in 0..initial_state.len() {
-            for j in 0..initial_state[0].len() {
-                if initial_state[i][j] == b'#' {
-                    new_space.add(&(i as i64, j as i64, 0, 0));
+        for (i, row) in initial_state.iter().enumerate() {
+            for (j, &cube) in row.iter().enumerate() {
+                if cube == b'#' {
+                    let mut point = [0; D];
+                    point[0] = i as i64;
+                    point[1] = j as i64;
+                    new_space.add(&point);
                 }
             }
         }
         new_space
     }
+
+    /// Number of active cubes in the whole space, not just the stored representatives.
+    fn len(&self) -> usize {
+        self.cubes.iter().map(Self::orbit_size).sum()
+    }
+
     fn next_cycle(self) -> Self {
         let mut new_space = Space {
             cubes: HashSet::new(),
         };
-        let empty_neighbors: HashSet<(i64, i64, i64, i64)> = self
+        let empty_neighbors: HashSet<Point<D>> = self
             .cubes
             .iter()
-            .map(|point| Self::neighbors(point).into_iter())
-            .flatten()
+            .flat_map(Self::neighbors)
+            .map(|point| Self::canonical(&point))
             .filter(|point| !self.cubes.contains(point))
             .collect();
         self.cubes.iter().for_each(|point| {
-            let occupied_neighbors = Self::neighbors(point)
-                .iter()
-                .filter(|neighbor_point| self.cubes.contains(neighbor_point))
-                .count();
+            let occupied_neighbors = self.occupied_neighbors(point);
             if occupied_neighbors == 2 || occupied_neighbors == 3 {
                 new_space.add(point);
             }
         });
         empty_neighbors.iter().for_each(|point| {
-            let occupied_neighbors = Self::neighbors(point)
-                .iter()
-                .filter(|neighbor_point| self.cubes.contains(neighbor_point))
-                .count();
-            if occupied_neighbors == 3 {
+            if self.occupied_neighbors(point) == 3 {
                 new_space.add(point);
             }
         });
         new_space
     }
-    fn add(&mut self, point: &(i64, i64, i64, i64)) {
-        self.cubes.insert(*point);
+
+    fn add(&mut self, point: &Point<D>) {
+        self.cubes.insert(Self::canonical(point));
+    }
+
+    fn occupied_neighbors(&self, point: &Point<D>) -> usize {
+        Self::neighbors(point)
+            .filter(|neighbor_point| self.cubes.contains(&Self::canonical(neighbor_point)))
+            .count()
+    }
+
+    fn neighbors(point: &Point<D>) -> impl Iterator<Item = Point<D>> {
+        let point = *point;
+        // count through every offset in {-1, 0, 1}^D, skipping all zeroes
+        (0..3usize.pow(D as u32))
+            .map(move |mut n| {
+                let mut neighbor = point;
+                for coord in neighbor.iter_mut() {
+                    *coord += (n % 3) as i64 - 1;
+                    n /= 3;
+                }
+                neighbor
+            })
+            .filter(move |neighbor| *neighbor != point)
+    }
+
+    fn canonical(point: &Point<D>) -> Point<D> {
+        let mut canonical = *point;
+        for coord in canonical[2..].iter_mut() {
+            *coord = coord.abs();
+        }
+        canonical[2..].sort_unstable();
+        canonical
     }
 
-    fn neighbors(point: &(i64, i64, i64, i64)) -> HashSet<(i64, i64, i64, i64)> {
-        let mut n = HashSet::new();
-        let (x, y, z, w) = point;
-        for i in x - 1..=x + 1 {
-            for j in y - 1..=y + 1 {
-                for k in z - 1..=z + 1 {
-                    for l in w - 1..=w + 1 {
-                        if (i, j, k, l) != *point {
-                            n.insert((i, j, k, l));
-                        }
+    /// Every active cube, undoing the symmetry reduction.
+    fn expand(&self) -> HashSet<Point<D>> {
+        let mut cubes = HashSet::new();
+        for cube in &self.cubes {
+            let mut pending = vec![*cube];
+            while let Some(point) = pending.pop() {
+                if !cubes.insert(point) {
+                    continue;
+                }
+                // a sign flip or swap of two extra dimensions generates the whole class
+                for i in 2..D {
+                    let mut flipped = point;
+                    flipped[i] = -flipped[i];
+                    pending.push(flipped);
+                    for j in i + 1..D {
+                        let mut swapped = point;
+                        swapped.swap(i, j);
+                        pending.push(swapped);
                     }
                 }
             }
         }
-        n
+        cubes
+    }
+
+    /// Number of points with the same canonical form as `point`.
+    fn orbit_size(point: &Point<D>) -> usize {
+        let extra = &point[2..];
+        let flips = 1 << extra.iter().filter(|&&c| c != 0).count();
+        // distinct orderings of the multiset of extra coordinates
+        let mut orderings = factorial(extra.len());
+        let mut run = 1;
+        for i in 1..=extra.len() {
+            if i < extra.len() && extra[i] == extra[i - 1] {
+                run += 1;
+            } else {
+                orderings /= factorial(run);
+                run = 1;
+            }
+        }
+        flips * orderings
     }
 }
+
+fn factorial(n: usize) -> usize {
+    (1..=n).product()
+}
@@ -0,0 +1,111 @@
+use crate::Point;
+use std::collections::HashSet;
+use std::fmt::Write;
+
+/// Smallest and largest coordinate along every dimension, `None` if there are no cubes.
+pub fn bounding_box<const D: usize>(cubes: &HashSet<Point<D>>) -> Option<(Point<D>, Point<D>)> {
+    let first = cubes.iter().next()?;
+    let (mut min, mut max) = (*first, *first);
+    for cube in cubes {
+        for d in 0..D {
+            min[d] = min[d].min(cube[d]);
+            max[d] = max[d].max(cube[d]);
+        }
+    }
+    Some((min, max))
+}
+
+fn dimension_name(d: usize) -> String {
+    match d {
+        0 => "y".into(),
+        1 => "x".into(),
+        2 => "z".into(),
+        3 => "w".into(),
+        _ => format!("d{}", d),
+    }
+}
+
+/// Every 2D slice in the bounding box, the way the puzzle text draws them.
+pub fn slices<const D: usize>(cubes: &HashSet<Point<D>>) -> String {
+    let mut out = String::new();
+    let (min, max) = match bounding_box(cubes) {
+        Some(bounds) => bounds,
+        None => return out,
+    };
+    // walk the extra coordinates like an odometer, first extra dimension fastest
+    let mut slice: Vec<i64> = min[2..].to_vec();
+    loop {
+        let header: Vec<String> = slice
+            .iter()
+            .enumerate()
+            .map(|(i, c)| format!("{}={}", dimension_name(i + 2), c))
+            .collect();
+        writeln!(out, "{}", header.join(", ")).unwrap();
+        for y in min[0]..=max[0] {
+            for x in min[1]..=max[1] {
+                let mut point = [0; D];
+                point[0] = y;
+                point[1] = x;
+                point[2..].copy_from_slice(&slice);
+                out.push(if cubes.contains(&point) { '#' } else { '.' });
+            }
+            out.push('\n');
+        }
+        out.push('\n');
+        let mut d = 0;
+        loop {
+            if d == slice.len() {
+                return out;
+            }
+            if slice[d] < max[d + 2] {
+                slice[d] += 1;
+                break;
+            }
+            slice[d] = min[d + 2];
+            d += 1;
+        }
+    }
+}
+
+/// ASCII PLY point cloud, with x, y, z as vertex positions and any further dimensions as
+/// extra integer properties.
+pub fn ply<const D: usize>(cubes: &HashSet<Point<D>>) -> String {
+    let mut out = String::new();
+    writeln!(out, "ply\nformat ascii 1.0\nelement vertex {}", cubes.len()).unwrap();
+    writeln!(out, "property float x\nproperty float y\nproperty float z").unwrap();
+    for d in 3..D {
+        writeln!(out, "property int {}", dimension_name(d)).unwrap();
+    }
+    writeln!(out, "end_header").unwrap();
+    for cube in sorted(cubes) {
+        write!(out, "{} {} {}", cube[1], cube[0], z(&cube)).unwrap();
+        for c in &cube[3.min(D)..] {
+            write!(out, " {}", c).unwrap();
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Wavefront OBJ point cloud, further dimensions are projected away.
+pub fn obj<const D: usize>(cubes: &HashSet<Point<D>>) -> String {
+    let mut out = String::new();
+    for cube in sorted(cubes) {
+        writeln!(out, "v {} {} {}", cube[1], cube[0], z(&cube)).unwrap();
+    }
+    out
+}
+
+fn z<const D: usize>(cube: &Point<D>) -> i64 {
+    if D > 2 {
+        cube[2]
+    } else {
+        0
+    }
+}
+
+fn sorted<const D: usize>(cubes: &HashSet<Point<D>>) -> Vec<Point<D>> {
+    let mut cubes: Vec<Point<D>> = cubes.iter().copied().collect();
+    cubes.sort_unstable();
+    cubes
+}
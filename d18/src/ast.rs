@@ -0,0 +1,122 @@
+use crate::numeric::{EvalError, Number};
+use crate::Rule;
+use pest::iterators::{Pair, Pairs};
+use pest::pratt_parser::PrattParser;
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    // kept as written, each numeric backend reads it its own way
+    Num(String),
+    Var(String),
+    Neg(Box<Expr>),
+    BinOp(Op, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Statement {
+    Let(String, Expr),
+    Expr(Expr),
+}
+
+impl Statement {
+    /// Builds a statement from a `calculation` parse, grouping operators with `parser`.
+    pub fn build(mut calculation: Pairs<Rule>, parser: &PrattParser<Rule>) -> Self {
+        let pair = calculation.next().unwrap();
+        match pair.as_rule() {
+            Rule::assignment => {
+                let mut inner = pair.into_inner();
+                let name = inner.next().unwrap().as_str().to_string();
+                let expr = Expr::build(inner.next().unwrap().into_inner(), parser);
+                Statement::Let(name, expr)
+            }
+            Rule::expr => Statement::Expr(Expr::build(pair.into_inner(), parser)),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl Expr {
+    pub fn build(expression: Pairs<Rule>, parser: &PrattParser<Rule>) -> Self {
+        parser
+            .map_primary(|pair: Pair<Rule>| match pair.as_rule() {
+                Rule::num => Expr::Num(pair.as_str().to_string()),
+                Rule::ident => Expr::Var(pair.as_str().to_string()),
+                Rule::expr => Self::build(pair.into_inner(), parser),
+                _ => unreachable!(),
+            })
+            .map_prefix(|_neg: Pair<Rule>, expr: Expr| Expr::Neg(Box::new(expr)))
+            .map_infix(|lhs: Expr, op: Pair<Rule>, rhs: Expr| {
+                let op = match op.as_rule() {
+                    Rule::add => Op::Add,
+                    Rule::subtract => Op::Subtract,
+                    Rule::multiply => Op::Multiply,
+                    Rule::divide => Op::Divide,
+                    _ => unreachable!(),
+                };
+                Expr::BinOp(op, Box::new(lhs), Box::new(rhs))
+            })
+            .parse(expression)
+    }
+
+    pub fn eval<N: Number>(&self, env: &HashMap<String, N>) -> Result<N, EvalError> {
+        match self {
+            Expr::Num(s) => N::parse(s),
+            Expr::Var(name) => env
+                .get(name)
+                .cloned()
+                .ok_or_else(|| EvalError::UnknownVariable(name.clone())),
+            Expr::Neg(expr) => expr.eval(env)?.neg(),
+            Expr::BinOp(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.eval(env)?, rhs.eval(env)?);
+                match op {
+                    Op::Add => lhs.add(&rhs),
+                    Op::Subtract => lhs.sub(&rhs),
+                    Op::Multiply => lhs.mul(&rhs),
+                    Op::Divide => lhs.div(&rhs),
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            Op::Add => "+",
+            Op::Subtract => "-",
+            Op::Multiply => "*",
+            Op::Divide => "/",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+/// Fully parenthesised, so the grouping chosen by the precedence table is visible.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Num(s) | Expr::Var(s) => write!(f, "{}", s),
+            Expr::Neg(expr) => write!(f, "(-{})", expr),
+            Expr::BinOp(op, lhs, rhs) => write!(f, "({} {} {})", lhs, op, rhs),
+        }
+    }
+}
+
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Statement::Let(name, expr) => write!(f, "let {} = {}", name, expr),
+            Statement::Expr(expr) => write!(f, "{}", expr),
+        }
+    }
+}
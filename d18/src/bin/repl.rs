@@ -0,0 +1,78 @@
+use d18::ast::Statement;
+use d18::{precedence, MathParser, Rule};
+use num_rational::BigRational;
+use pest::Parser;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{self, BufRead, Write};
+
+// Evaluates one statement per line with exact rational arithmetic.
+//
+//     :prec SPEC   switch precedence table, see `precedence::parse`
+//     :vars        list bindings
+//     :quit        leave, as does end of input
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut spec = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "add-first".to_string());
+    let mut parser = precedence::parse(&spec)?;
+    let mut env: HashMap<String, BigRational> = HashMap::new();
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("{}> ", spec);
+        io::stdout().flush()?;
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => break,
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(new_spec) = line.strip_prefix(":prec") {
+            match precedence::parse(new_spec.trim()) {
+                Ok(new_parser) => {
+                    parser = new_parser;
+                    spec = new_spec.trim().to_string();
+                }
+                Err(e) => println!("error: {}", e),
+            }
+            continue;
+        }
+        match line {
+            ":quit" => break,
+            ":vars" => {
+                let mut vars: Vec<_> = env.iter().collect();
+                vars.sort();
+                for (name, value) in vars {
+                    println!("{} = {}", name, value);
+                }
+                continue;
+            }
+            _ => (),
+        }
+        let calculation = match MathParser::parse(Rule::calculation, line) {
+            Ok(calculation) => calculation,
+            Err(e) => {
+                println!("{}", e);
+                continue;
+            }
+        };
+        match Statement::build(calculation, &parser) {
+            Statement::Let(name, expr) => match expr.eval(&env) {
+                Ok(value) => {
+                    println!("{} = {} = {}", name, expr, value);
+                    env.insert(name, value);
+                }
+                Err(e) => println!("error: {}", e),
+            },
+            Statement::Expr(expr) => match expr.eval::<BigRational>(&env) {
+                Ok(value) => println!("{} = {}", expr, value),
+                Err(e) => println!("error: {}", e),
+            },
+        }
+    }
+    println!();
+    Ok(())
+}
@@ -0,0 +1,10 @@
+#[macro_use]
+extern crate pest_derive;
+
+pub mod ast;
+pub mod numeric;
+pub mod precedence;
+
+#[derive(Parser)]
+#[grammar = "math.pest"]
+pub struct MathParser;
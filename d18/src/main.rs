@@ -1,58 +1,95 @@
-#[macro_use]
-extern crate lazy_static;
-#[macro_use]
-extern crate pest_derive;
-
-use pest::iterators::{Pair, Pairs};
-use pest::prec_climber::{Assoc, Operator, PrecClimber};
+use d18::ast::Statement;
+use d18::numeric::{EvalError, Number};
+use d18::{precedence, MathParser, Rule};
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use pest::pratt_parser::PrattParser;
 use pest::Parser;
+use std::collections::HashMap;
 use std::error::Error;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
-#[derive(Parser)]
-#[grammar = "math.pest"]
-struct MathParser;
-
-lazy_static! {
-    static ref PREC_CLIMBER: PrecClimber<Rule> = {
-        use Assoc::*;
-        use Rule::*;
-
-        PrecClimber::new(vec![
-            Operator::new(subtract, Left)
-                | Operator::new(multiply, Left)
-                | Operator::new(divide, Left),
-            Operator::new(add, Left),
-        ])
-    };
-}
 fn main() -> Result<(), Box<dyn Error>> {
-    let filename = std::env::args().nth(1).unwrap();
+    let mut args = std::env::args().skip(1);
+    let filename = args.next().unwrap();
+    let mut mode = "rational".to_string();
+    let mut show = false;
+    // each further argument is a precedence table spec or preset name
+    let mut specs: Vec<String> = vec![];
+    for arg in args {
+        match arg.strip_prefix("--mode=") {
+            Some(m) => mode = m.to_string(),
+            None if arg == "--show" => show = true,
+            None => specs.push(arg),
+        }
+    }
+    if specs.is_empty() {
+        specs.push("add-first".into());
+    }
+    let parsers: Result<Vec<PrattParser<Rule>>, Box<dyn Error>> =
+        specs.iter().map(|spec| precedence::parse(spec)).collect();
+    let parsers = parsers?;
     let input = File::open(filename)?;
-    let buffered = BufReader::new(input);
-    let v: f64 = buffered
-        .lines()
-        .map(|r| eval(MathParser::parse(Rule::calculation, &r.unwrap()).unwrap()))
-        .sum();
-    dbg!(v);
+    let lines: Result<Vec<String>, _> = BufReader::new(input).lines().collect();
+    let lines = lines?;
+    let totals = match mode.as_str() {
+        "i128" => run::<i128>(&lines, &parsers, show)?,
+        "big" => run::<BigInt>(&lines, &parsers, show)?,
+        "rational" => run::<BigRational>(&lines, &parsers, show)?,
+        "f64" => run::<f64>(&lines, &parsers, show)?,
+        _ => return Err(format!("unknown mode {:?}", mode).into()),
+    };
+    for (spec, total) in specs.iter().zip(totals) {
+        println!("{}: {}", spec, total);
+    }
     Ok(())
 }
 
-fn eval(expression: Pairs<Rule>) -> f64 {
-    PREC_CLIMBER.climb(
-        expression,
-        |pair: Pair<Rule>| match pair.as_rule() {
-            Rule::num => pair.as_str().parse::<f64>().unwrap(),
-            Rule::expr => eval(pair.into_inner()),
-            _ => unreachable!(),
-        },
-        |lhs: f64, op: Pair<Rule>, rhs: f64| match op.as_rule() {
-            Rule::add => lhs + rhs,
-            Rule::subtract => lhs - rhs,
-            Rule::multiply => lhs * rhs,
-            Rule::divide => lhs / rhs,
-            _ => unreachable!(),
-        },
-    )
+/// Sums every expression line under each precedence table, reporting lines where the tables
+/// disagree. `let` lines bind a variable for the lines after them.
+fn run<N: Number>(
+    lines: &[String],
+    parsers: &[PrattParser<Rule>],
+    show: bool,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut totals = vec![N::zero(); parsers.len()];
+    let mut envs: Vec<HashMap<String, N>> = vec![HashMap::new(); parsers.len()];
+    for (i, line) in lines.iter().enumerate() {
+        let calculation = MathParser::parse(Rule::calculation, line)
+            .map_err(|e| format!("line {}: {}", i + 1, e))?;
+        let statements: Vec<Statement> = parsers
+            .iter()
+            .map(|parser| Statement::build(calculation.clone(), parser))
+            .collect();
+        if show {
+            for statement in &statements {
+                println!("line {}: {}", i + 1, statement);
+            }
+        }
+        let results: Result<Vec<Option<N>>, EvalError> = statements
+            .into_iter()
+            .zip(envs.iter_mut())
+            .map(|(statement, env)| match statement {
+                Statement::Let(name, expr) => {
+                    env.insert(name, expr.eval(env)?);
+                    Ok(None)
+                }
+                Statement::Expr(expr) => expr.eval(env).map(Some),
+            })
+            .collect();
+        let results = results.map_err(|e| format!("line {}: {}", i + 1, e))?;
+        let results: Vec<N> = match results.into_iter().collect() {
+            Some(results) => results,
+            None => continue,
+        };
+        if results.iter().any(|r| *r != results[0]) {
+            let shown: Vec<String> = results.iter().map(N::to_string).collect();
+            println!("line {}: {} => {}", i + 1, line, shown.join(", "));
+        }
+        for (total, result) in totals.iter_mut().zip(results) {
+            *total = total.add(&result)?;
+        }
+    }
+    Ok(totals.iter().map(N::to_string).collect())
 }
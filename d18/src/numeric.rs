@@ -0,0 +1,192 @@
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{Pow, ToPrimitive, Zero};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EvalError {
+    Overflow,
+    DivisionByZero,
+    UnknownVariable(String),
+    /// The literal can't be represented, e.g. `1.5` in an integer mode.
+    BadNumber(String),
+}
+
+impl Error for EvalError {}
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::Overflow => write!(f, "overflow"),
+            EvalError::DivisionByZero => write!(f, "division by zero"),
+            EvalError::UnknownVariable(name) => write!(f, "unknown variable {:?}", name),
+            EvalError::BadNumber(s) => write!(f, "can't represent {:?}", s),
+        }
+    }
+}
+
+/// Arithmetic the evaluator can run on. Integer division truncates towards zero.
+pub trait Number: Sized + Clone + PartialEq + fmt::Display {
+    fn zero() -> Self;
+    fn parse(s: &str) -> Result<Self, EvalError>;
+    fn add(&self, rhs: &Self) -> Result<Self, EvalError>;
+    fn sub(&self, rhs: &Self) -> Result<Self, EvalError>;
+    fn mul(&self, rhs: &Self) -> Result<Self, EvalError>;
+    fn div(&self, rhs: &Self) -> Result<Self, EvalError>;
+    fn neg(&self) -> Result<Self, EvalError> {
+        Self::zero().sub(self)
+    }
+}
+
+// Powers of ten past this are refused rather than computed, `1e999999999` would take forever.
+const MAX_EXPONENT: u32 = 10_000;
+
+// Reads a `num` literal exactly: optional sign, digits, optional fraction and exponent. The
+// value has to be within `max_exponent` powers of ten of its digits, or it's an overflow.
+fn parse_exact(s: &str, max_exponent: u32) -> Result<BigRational, EvalError> {
+    let bad = || EvalError::BadNumber(s.to_string());
+    let lower = s.to_ascii_lowercase();
+    let mut parts = lower.splitn(2, 'e');
+    let mantissa = parts.next().unwrap();
+    let exponent: i32 = match parts.next() {
+        Some(e) => e.parse().map_err(|_| bad())?,
+        None => 0,
+    };
+    let mut digits = mantissa.splitn(2, '.');
+    let whole = digits.next().unwrap();
+    let fraction = digits.next().unwrap_or("");
+    let numer: BigInt = format!("{}{}", whole, fraction)
+        .parse()
+        .map_err(|_| bad())?;
+    let exponent = i32::try_from(fraction.len())
+        .ok()
+        .and_then(|len| exponent.checked_sub(len))
+        .ok_or(EvalError::Overflow)?;
+    if numer.is_zero() {
+        return Ok(Zero::zero());
+    }
+    if exponent.unsigned_abs() > max_exponent {
+        return Err(EvalError::Overflow);
+    }
+    let scale = BigInt::from(10).pow(exponent.unsigned_abs());
+    if exponent >= 0 {
+        Ok(BigRational::from_integer(numer * scale))
+    } else {
+        Ok(BigRational::new(numer, scale))
+    }
+}
+
+fn nonzero<T: Zero>(rhs: &T) -> Result<(), EvalError> {
+    if rhs.is_zero() {
+        Err(EvalError::DivisionByZero)
+    } else {
+        Ok(())
+    }
+}
+
+impl Number for i128 {
+    fn zero() -> Self {
+        0
+    }
+    fn parse(s: &str) -> Result<Self, EvalError> {
+        // 10^38 is the largest power of ten that fits
+        let n = parse_exact(s, 38)?;
+        if !n.is_integer() {
+            return Err(EvalError::BadNumber(s.to_string()));
+        }
+        n.to_integer().to_i128().ok_or(EvalError::Overflow)
+    }
+    fn add(&self, rhs: &Self) -> Result<Self, EvalError> {
+        self.checked_add(*rhs).ok_or(EvalError::Overflow)
+    }
+    fn sub(&self, rhs: &Self) -> Result<Self, EvalError> {
+        self.checked_sub(*rhs).ok_or(EvalError::Overflow)
+    }
+    fn mul(&self, rhs: &Self) -> Result<Self, EvalError> {
+        self.checked_mul(*rhs).ok_or(EvalError::Overflow)
+    }
+    fn div(&self, rhs: &Self) -> Result<Self, EvalError> {
+        nonzero(rhs)?;
+        self.checked_div(*rhs).ok_or(EvalError::Overflow)
+    }
+}
+
+impl Number for BigInt {
+    fn zero() -> Self {
+        Zero::zero()
+    }
+    fn parse(s: &str) -> Result<Self, EvalError> {
+        let n = parse_exact(s, MAX_EXPONENT)?;
+        if !n.is_integer() {
+            return Err(EvalError::BadNumber(s.to_string()));
+        }
+        Ok(n.to_integer())
+    }
+    fn add(&self, rhs: &Self) -> Result<Self, EvalError> {
+        Ok(self + rhs)
+    }
+    fn sub(&self, rhs: &Self) -> Result<Self, EvalError> {
+        Ok(self - rhs)
+    }
+    fn mul(&self, rhs: &Self) -> Result<Self, EvalError> {
+        Ok(self * rhs)
+    }
+    fn div(&self, rhs: &Self) -> Result<Self, EvalError> {
+        nonzero(rhs)?;
+        Ok(self / rhs)
+    }
+}
+
+impl Number for BigRational {
+    fn zero() -> Self {
+        Zero::zero()
+    }
+    fn parse(s: &str) -> Result<Self, EvalError> {
+        parse_exact(s, MAX_EXPONENT)
+    }
+    fn add(&self, rhs: &Self) -> Result<Self, EvalError> {
+        Ok(self + rhs)
+    }
+    fn sub(&self, rhs: &Self) -> Result<Self, EvalError> {
+        Ok(self - rhs)
+    }
+    fn mul(&self, rhs: &Self) -> Result<Self, EvalError> {
+        Ok(self * rhs)
+    }
+    fn div(&self, rhs: &Self) -> Result<Self, EvalError> {
+        nonzero(rhs)?;
+        Ok(self / rhs)
+    }
+}
+
+// the old behaviour, with infinities and NaNs turned into errors
+impl Number for f64 {
+    fn zero() -> Self {
+        0.0
+    }
+    fn parse(s: &str) -> Result<Self, EvalError> {
+        s.parse().map_err(|_| EvalError::BadNumber(s.to_string()))
+    }
+    fn add(&self, rhs: &Self) -> Result<Self, EvalError> {
+        finite(self + rhs)
+    }
+    fn sub(&self, rhs: &Self) -> Result<Self, EvalError> {
+        finite(self - rhs)
+    }
+    fn mul(&self, rhs: &Self) -> Result<Self, EvalError> {
+        finite(self * rhs)
+    }
+    fn div(&self, rhs: &Self) -> Result<Self, EvalError> {
+        nonzero(rhs)?;
+        finite(self / rhs)
+    }
+}
+
+fn finite(n: f64) -> Result<f64, EvalError> {
+    if n.is_finite() {
+        Ok(n)
+    } else {
+        Err(EvalError::Overflow)
+    }
+}
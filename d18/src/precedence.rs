@@ -0,0 +1,65 @@
+use crate::Rule;
+use pest::pratt_parser::{Assoc, Op, PrattParser};
+use std::error::Error;
+
+/// Named tables, usable anywhere a spec is.
+pub const PRESETS: &[(&str, &str)] = &[
+    // part 1, strictly left to right
+    ("ltr", "+-*/"),
+    // part 2, addition binds tighter than everything else
+    ("add-first", "-*/ < +"),
+    ("standard", "+- < */"),
+];
+
+/// Builds a Pratt parser from a spec like `+- < */`: operator levels from loosest to
+/// tightest separated by `<`, a level prefixed with `r:` is right-associative. Unary minus
+/// always binds tighter than any of them.
+pub fn parse(spec: &str) -> Result<PrattParser<Rule>, Box<dyn Error>> {
+    let spec = PRESETS
+        .iter()
+        .find(|(name, _)| *name == spec)
+        .map_or(spec, |(_, table)| table);
+    let mut seen = vec![];
+    let levels: Result<Vec<Op<Rule>>, Box<dyn Error>> = spec
+        .split('<')
+        .map(|level| {
+            let level = level.trim();
+            let (assoc, ops) = match level.strip_prefix("r:") {
+                Some(ops) => (Assoc::Right, ops),
+                None => (Assoc::Left, level),
+            };
+            let operators: Result<Vec<Op<Rule>>, Box<dyn Error>> = ops
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .map(|c| {
+                    if seen.contains(&c) {
+                        return Err(format!("{:?} appears twice in {:?}", c, spec).into());
+                    }
+                    seen.push(c);
+                    Ok(Op::infix(operator_rule(c)?, assoc))
+                })
+                .collect();
+            operators?
+                .into_iter()
+                .reduce(|a, b| a | b)
+                .ok_or_else(|| format!("empty level in {:?}", spec).into())
+        })
+        .collect();
+    if let Some(missing) = "+-*/".chars().find(|c| !seen.contains(c)) {
+        return Err(format!("{:?} is missing from {:?}", missing, spec).into());
+    }
+    let parser = levels?
+        .into_iter()
+        .fold(PrattParser::new(), |parser, level| parser.op(level));
+    Ok(parser.op(Op::prefix(Rule::neg)))
+}
+
+fn operator_rule(c: char) -> Result<Rule, Box<dyn Error>> {
+    match c {
+        '+' => Ok(Rule::add),
+        '-' => Ok(Rule::subtract),
+        '*' => Ok(Rule::multiply),
+        '/' => Ok(Rule::divide),
+        _ => Err(format!("unknown operator {:?}", c).into()),
+    }
+}
@@ -0,0 +1,291 @@
+use crate::rules::{Body, ParseError, RuleSet};
+use rand::Rng;
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+
+const DEAD: usize = usize::MAX;
+
+/// Fails with the offending cycle if rule `id` can reach itself, in which case its language
+/// may not be regular.
+pub fn check_acyclic(rules: &RuleSet, id: usize) -> Result<(), Box<dyn Error>> {
+    fn visit(
+        rules: &RuleSet,
+        id: usize,
+        path: &mut Vec<usize>,
+        done: &mut BTreeSet<usize>,
+    ) -> Result<(), Box<dyn Error>> {
+        if done.contains(&id) {
+            return Ok(());
+        }
+        if let Some(i) = path.iter().position(|&r| r == id) {
+            let cycle: Vec<String> = path[i..]
+                .iter()
+                .chain(Some(&id))
+                .map(usize::to_string)
+                .collect();
+            return Err(Box::new(ParseError(format!(
+                "rules are recursive: {}",
+                cycle.join(" -> ")
+            ))));
+        }
+        path.push(id);
+        if let Some(Body::Alternatives(alternatives)) = rules.rules.get(&id) {
+            for &rule in alternatives.iter().flatten() {
+                visit(rules, rule, path, done)?;
+            }
+        }
+        path.pop();
+        done.insert(id);
+        Ok(())
+    }
+    visit(rules, id, &mut vec![], &mut BTreeSet::new())
+}
+
+/// A regular expression for rule `id`, in the syntax most engines share.
+pub fn regex(rules: &RuleSet, id: usize) -> Result<String, Box<dyn Error>> {
+    check_acyclic(rules, id)?;
+    let mut memo = HashMap::new();
+    Ok(format!("^{}$", regex_rule(rules, id, &mut memo)))
+}
+
+fn regex_rule(rules: &RuleSet, id: usize, memo: &mut HashMap<usize, String>) -> String {
+    if let Some(regex) = memo.get(&id) {
+        return regex.clone();
+    }
+    let regex = match &rules.rules[&id] {
+        Body::Literal(literal) => literal
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_string()
+                } else {
+                    format!("\\{}", c)
+                }
+            })
+            .collect(),
+        Body::Alternatives(alternatives) => {
+            let alternatives: Vec<String> = alternatives
+                .iter()
+                .map(|sequence| {
+                    sequence
+                        .iter()
+                        .map(|&r| regex_rule(rules, r, memo))
+                        .collect()
+                })
+                .collect();
+            if alternatives.len() == 1 {
+                alternatives.into_iter().next().unwrap()
+            } else {
+                format!("(?:{})", alternatives.join("|"))
+            }
+        }
+    };
+    memo.insert(id, regex.clone());
+    regex
+}
+
+#[derive(Debug, Default)]
+struct Nfa {
+    epsilon: Vec<Vec<usize>>,
+    edges: Vec<Vec<(u8, usize)>>,
+}
+
+impl Nfa {
+    fn state(&mut self) -> usize {
+        self.epsilon.push(vec![]);
+        self.edges.push(vec![]);
+        self.epsilon.len() - 1
+    }
+
+    // Thompson construction, returns (start, end)
+    fn build(&mut self, rules: &RuleSet, id: usize) -> (usize, usize) {
+        let start = self.state();
+        let mut end = start;
+        match &rules.rules[&id] {
+            Body::Literal(literal) => {
+                for &b in literal.as_bytes() {
+                    let next = self.state();
+                    self.edges[end].push((b, next));
+                    end = next;
+                }
+            }
+            Body::Alternatives(alternatives) => {
+                end = self.state();
+                for sequence in alternatives {
+                    let mut last = start;
+                    for &rule in sequence {
+                        let (s, e) = self.build(rules, rule);
+                        self.epsilon[last].push(s);
+                        last = e;
+                    }
+                    self.epsilon[last].push(end);
+                }
+            }
+        }
+        (start, end)
+    }
+
+    fn closure(&self, states: impl IntoIterator<Item = usize>) -> Vec<usize> {
+        let mut closure: BTreeSet<usize> = BTreeSet::new();
+        let mut stack: Vec<usize> = states.into_iter().collect();
+        while let Some(state) = stack.pop() {
+            if closure.insert(state) {
+                stack.extend(&self.epsilon[state]);
+            }
+        }
+        closure.into_iter().collect()
+    }
+}
+
+/// Deterministic automaton for a non-recursive rule, matching a message in a single pass.
+#[derive(Debug, Clone)]
+pub struct Dfa {
+    // transitions[state][byte], `DEAD` if there's none
+    transitions: Vec<Vec<usize>>,
+    accepting: Vec<bool>,
+}
+
+impl Dfa {
+    /// Compiles rule `id` via an NFA and the subset construction.
+    pub fn compile(rules: &RuleSet, id: usize) -> Result<Self, Box<dyn Error>> {
+        check_acyclic(rules, id)?;
+        let mut nfa = Nfa::default();
+        let (start, end) = nfa.build(rules, id);
+        let mut ids: HashMap<Vec<usize>, usize> = HashMap::new();
+        let mut subsets: Vec<Vec<usize>> = vec![];
+        let mut dfa = Dfa {
+            transitions: vec![],
+            accepting: vec![],
+        };
+        let first = nfa.closure(Some(start));
+        ids.insert(first.clone(), 0);
+        subsets.push(first);
+        let mut i = 0;
+        while i < subsets.len() {
+            let subset = subsets[i].clone();
+            let mut row = vec![DEAD; 256];
+            let mut targets: HashMap<u8, Vec<usize>> = HashMap::new();
+            for &state in &subset {
+                for &(b, next) in &nfa.edges[state] {
+                    targets.entry(b).or_default().push(next);
+                }
+            }
+            for (b, next) in targets {
+                let next = nfa.closure(next);
+                let len = subsets.len();
+                let id = *ids.entry(next.clone()).or_insert(len);
+                if id == len {
+                    subsets.push(next);
+                }
+                row[b as usize] = id;
+            }
+            dfa.transitions.push(row);
+            dfa.accepting.push(subset.contains(&end));
+            i += 1;
+        }
+        Ok(dfa)
+    }
+
+    pub fn states(&self) -> usize {
+        self.transitions.len()
+    }
+
+    pub fn matches(&self, message: &str) -> bool {
+        let mut state = 0;
+        for &b in message.as_bytes() {
+            state = self.transitions[state][b as usize];
+            if state == DEAD {
+                return false;
+            }
+        }
+        self.accepting[state]
+    }
+
+    // completions[k][state]: how many strings of length k lead from state to acceptance
+    fn completions(&self, max_len: usize) -> Vec<Vec<u128>> {
+        let mut completions = vec![self
+            .accepting
+            .iter()
+            .map(|&a| a as u128)
+            .collect::<Vec<_>>()];
+        for k in 1..=max_len {
+            let row = self
+                .transitions
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .filter(|&&next| next != DEAD)
+                        .map(|&next| completions[k - 1][next])
+                        .fold(0u128, u128::saturating_add)
+                })
+                .collect();
+            completions.push(row);
+        }
+        completions
+    }
+
+    /// Number of distinct accepted messages of each length up to `max_len`.
+    pub fn count_by_length(&self, max_len: usize) -> Vec<u128> {
+        self.completions(max_len).iter().map(|row| row[0]).collect()
+    }
+
+    /// Up to `limit` accepted messages of length `len`, in lexicographic order.
+    pub fn strings(&self, len: usize, limit: usize) -> Vec<String> {
+        let completions = self.completions(len);
+        let mut strings = vec![];
+        let mut prefix = vec![];
+        self.walk(0, len, &completions, &mut prefix, limit, &mut strings);
+        strings
+    }
+
+    fn walk(
+        &self,
+        state: usize,
+        remaining: usize,
+        completions: &[Vec<u128>],
+        prefix: &mut Vec<u8>,
+        limit: usize,
+        strings: &mut Vec<String>,
+    ) {
+        if strings.len() >= limit || completions[remaining][state] == 0 {
+            return;
+        }
+        if remaining == 0 {
+            strings.push(String::from_utf8_lossy(prefix).into_owned());
+            return;
+        }
+        for (b, &next) in self.transitions[state].iter().enumerate() {
+            if next != DEAD {
+                prefix.push(b as u8);
+                self.walk(next, remaining - 1, completions, prefix, limit, strings);
+                prefix.pop();
+            }
+        }
+    }
+
+    /// An accepted message of length `len`, every one equally likely.
+    pub fn sample<R: Rng>(&self, len: usize, rng: &mut R) -> Option<String> {
+        let completions = self.completions(len);
+        if completions[len][0] == 0 {
+            return None;
+        }
+        let mut state = 0;
+        let mut message = vec![];
+        for remaining in (0..len).rev() {
+            let mut pick = rng.gen_range(0..completions[remaining + 1][state]);
+            for (b, &next) in self.transitions[state].iter().enumerate() {
+                if next == DEAD {
+                    continue;
+                }
+                let weight = completions[remaining][next];
+                if pick < weight {
+                    message.push(b as u8);
+                    state = next;
+                    break;
+                }
+                pick -= weight;
+            }
+        }
+        Some(String::from_utf8_lossy(&message).into_owned())
+    }
+}
@@ -0,0 +1,483 @@
+use crate::rules::{Body, RuleSet};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+// `rule -> alternatives[alternative]` with `dot` symbols matched, started at `origin`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Item {
+    rule: usize,
+    alternative: usize,
+    dot: usize,
+    origin: usize,
+}
+
+/// Earley recognition of a message against a rule set. Unlike the backtracking matcher it
+/// copes with any recursion, left recursion and empty alternatives included.
+pub struct Parse<'a> {
+    rules: &'a RuleSet,
+    start: usize,
+    message: &'a [u8],
+    // how far some prefix of the message could be parsed, and the literals that could
+    // have come next there
+    furthest: usize,
+    expected: Vec<String>,
+    // (rule, start, end) for every span some rule derives
+    spans: HashSet<(usize, usize, usize)>,
+    counts: HashMap<(usize, usize, usize), Count>,
+    in_progress: HashSet<(usize, usize, usize)>,
+}
+
+/// Number of parse trees. Grammars with cycles like `1: 1 | "a"` have infinitely many,
+/// finite counts saturate at `u128::MAX`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Count {
+    Finite(u128),
+    Infinite,
+}
+
+impl Count {
+    fn add(self, other: Count) -> Count {
+        match (self, other) {
+            (Count::Finite(a), Count::Finite(b)) => Count::Finite(a.saturating_add(b)),
+            _ => Count::Infinite,
+        }
+    }
+
+    fn mul(self, other: Count) -> Count {
+        match (self, other) {
+            (Count::Finite(0), _) | (_, Count::Finite(0)) => Count::Finite(0),
+            (Count::Finite(a), Count::Finite(b)) => Count::Finite(a.saturating_mul(b)),
+            _ => Count::Infinite,
+        }
+    }
+}
+
+impl fmt::Display for Count {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Count::Finite(n) => write!(f, "{}", n),
+            Count::Infinite => write!(f, "infinitely many"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Tree {
+    Literal(usize, String),
+    Node {
+        rule: usize,
+        alternative: usize,
+        children: Vec<Tree>,
+    },
+}
+
+impl fmt::Display for Tree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Tree::Literal(rule, literal) => write!(f, "{}:{:?}", rule, literal),
+            Tree::Node { rule, children, .. } => {
+                write!(f, "{}(", rule)?;
+                for (i, child) in children.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}", child)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl<'a> Parse<'a> {
+    /// Parses `message` as rule `start`.
+    pub fn new(rules: &'a RuleSet, start: usize, message: &'a str) -> Self {
+        let message = message.as_bytes();
+        let nullable = rules.nullable();
+        let mut chart: Vec<Vec<Item>> = vec![vec![]; message.len() + 1];
+        let mut seen: Vec<HashSet<Item>> = vec![HashSet::new(); message.len() + 1];
+        let mut spans = HashSet::new();
+        let mut predicted: Vec<HashSet<usize>> = vec![HashSet::new(); message.len() + 1];
+        predicted[0].insert(start);
+        predict(rules, message, start, 0, &mut chart, &mut seen);
+        for pos in 0..=message.len() {
+            let mut i = 0;
+            while i < chart[pos].len() {
+                let item = chart[pos][i];
+                i += 1;
+                let sequence = match rules.rules.get(&item.rule) {
+                    Some(Body::Alternatives(alternatives)) => &alternatives[item.alternative],
+                    Some(Body::Literal(_)) => {
+                        // scanned literals arrive complete
+                        complete(item, pos, &mut chart, &mut seen, &mut spans, rules);
+                        continue;
+                    }
+                    None => continue,
+                };
+                match sequence.get(item.dot) {
+                    None => complete(item, pos, &mut chart, &mut seen, &mut spans, rules),
+                    Some(&next) => {
+                        if predicted[pos].insert(next) {
+                            predict(rules, message, next, pos, &mut chart, &mut seen);
+                        }
+                        // the next rule can match nothing, skip over it
+                        if nullable.contains(&next) {
+                            let skipped = Item {
+                                dot: item.dot + 1,
+                                ..item
+                            };
+                            add(&mut chart, &mut seen, pos, skipped);
+                        }
+                        // it may already have completed here
+                        if spans.contains(&(next, pos, pos)) {
+                            let advanced = Item {
+                                dot: item.dot + 1,
+                                ..item
+                            };
+                            add(&mut chart, &mut seen, pos, advanced);
+                        }
+                    }
+                }
+            }
+        }
+        let furthest = (0..=message.len())
+            .rev()
+            .find(|&pos| !chart[pos].is_empty())
+            .unwrap_or(0);
+        let mut expected: Vec<String> = predicted[furthest]
+            .iter()
+            .filter_map(|rule| match rules.rules.get(rule) {
+                Some(Body::Literal(literal)) => Some(literal.clone()),
+                _ => None,
+            })
+            .collect();
+        expected.sort();
+        expected.dedup();
+        Parse {
+            rules,
+            start,
+            message,
+            furthest,
+            expected,
+            spans,
+            counts: HashMap::new(),
+            in_progress: HashSet::new(),
+        }
+    }
+
+    /// Whether the start rule derives the whole message.
+    pub fn matches(&self) -> bool {
+        self.spans.contains(&(self.start, 0, self.message.len()))
+    }
+
+    /// Number of ways the start rule derives the whole message.
+    pub fn count(&mut self) -> Count {
+        self.count_span(self.start, 0, self.message.len())
+    }
+
+    fn count_span(&mut self, rule: usize, start: usize, end: usize) -> Count {
+        let key = (rule, start, end);
+        if !self.spans.contains(&key) {
+            return Count::Finite(0);
+        }
+        if let Some(&count) = self.counts.get(&key) {
+            return count;
+        }
+        if !self.in_progress.insert(key) {
+            // derives itself over the same span, round and round
+            return Count::Infinite;
+        }
+        let count = match self.rules.rules[&rule].clone() {
+            Body::Literal(_) => Count::Finite(1),
+            Body::Alternatives(alternatives) => alternatives
+                .iter()
+                .map(|sequence| self.count_sequence(sequence, start, end))
+                .fold(Count::Finite(0), Count::add),
+        };
+        self.in_progress.remove(&key);
+        self.counts.insert(key, count);
+        count
+    }
+
+    fn count_sequence(&mut self, sequence: &[usize], start: usize, end: usize) -> Count {
+        match sequence.split_first() {
+            None => Count::Finite((start == end) as u128),
+            Some((&first, rest)) => {
+                let mut total = Count::Finite(0);
+                for mid in start..=end {
+                    if !self.spans.contains(&(first, start, mid)) {
+                        continue;
+                    }
+                    let tail = self.count_sequence(rest, mid, end);
+                    if tail == Count::Finite(0) {
+                        continue;
+                    }
+                    total = total.add(self.count_span(first, start, mid).mul(tail));
+                }
+                total
+            }
+        }
+    }
+
+    /// Up to `limit` parse trees of the whole message, leaving out the infinitely many that
+    /// go round a cycle.
+    pub fn trees(&self, limit: usize) -> Vec<Tree> {
+        let mut active = HashSet::new();
+        self.trees_span(self.start, 0, self.message.len(), limit, &mut active)
+    }
+
+    /// Which alternatives of which rules matched which part of the message, one rule per
+    /// line, or where matching got stuck.
+    pub fn explain(&self) -> String {
+        let text = String::from_utf8_lossy(self.message);
+        match self.trees(1).first() {
+            Some(tree) => {
+                let mut out = String::new();
+                explain_tree(tree, &text, 0, 0, &mut out);
+                out
+            }
+            None if self.furthest == self.message.len() => format!(
+                "{:?} is a prefix of a match but ends early, expected one of {:?}\n",
+                text, self.expected
+            ),
+            None => format!(
+                "{:?} stops matching at position {} after {:?}, expected one of {:?}\n",
+                text,
+                self.furthest,
+                &text[..self.furthest],
+                self.expected
+            ),
+        }
+    }
+
+    fn trees_span(
+        &self,
+        rule: usize,
+        start: usize,
+        end: usize,
+        limit: usize,
+        active: &mut HashSet<(usize, usize, usize)>,
+    ) -> Vec<Tree> {
+        let key = (rule, start, end);
+        if limit == 0 || !self.spans.contains(&key) || !active.insert(key) {
+            return vec![];
+        }
+        let trees = match &self.rules.rules[&rule] {
+            Body::Literal(literal) => vec![Tree::Literal(rule, literal.clone())],
+            Body::Alternatives(alternatives) => {
+                let mut trees = vec![];
+                for (alternative, sequence) in alternatives.iter().enumerate() {
+                    for children in
+                        self.trees_sequence(sequence, start, end, limit - trees.len(), active)
+                    {
+                        trees.push(Tree::Node {
+                            rule,
+                            alternative,
+                            children,
+                        });
+                    }
+                    if trees.len() >= limit {
+                        break;
+                    }
+                }
+                trees
+            }
+        };
+        active.remove(&key);
+        trees
+    }
+
+    fn trees_sequence(
+        &self,
+        sequence: &[usize],
+        start: usize,
+        end: usize,
+        limit: usize,
+        active: &mut HashSet<(usize, usize, usize)>,
+    ) -> Vec<Vec<Tree>> {
+        let (&first, rest) = match sequence.split_first() {
+            None if start == end => return vec![vec![]],
+            None => return vec![],
+            Some(split) => split,
+        };
+        let mut results = vec![];
+        for mid in start..=end {
+            if results.len() >= limit {
+                break;
+            }
+            if !self.spans.contains(&(first, start, mid)) {
+                continue;
+            }
+            let tails = self.trees_sequence(rest, mid, end, limit, active);
+            if tails.is_empty() {
+                continue;
+            }
+            for head in self.trees_span(first, start, mid, limit, active) {
+                for tail in &tails {
+                    if results.len() >= limit {
+                        break;
+                    }
+                    let mut children = vec![head.clone()];
+                    children.extend(tail.iter().cloned());
+                    results.push(children);
+                }
+            }
+        }
+        results
+    }
+}
+
+// writes one line per node, returns where the node ends
+fn explain_tree(tree: &Tree, text: &str, start: usize, depth: usize, out: &mut String) -> usize {
+    let indent = "  ".repeat(depth);
+    match tree {
+        Tree::Literal(rule, literal) => {
+            let end = start + literal.len();
+            out.push_str(&format!(
+                "{}{} {:?} [{}..{}]\n",
+                indent, rule, literal, start, end
+            ));
+            end
+        }
+        Tree::Node {
+            rule,
+            alternative,
+            children,
+        } => {
+            let line = out.len();
+            let mut end = start;
+            for child in children {
+                end = explain_tree(child, text, end, depth + 1, out);
+            }
+            let header = format!(
+                "{}{} alternative {} {:?} [{}..{}]\n",
+                indent,
+                rule,
+                alternative,
+                &text[start..end],
+                start,
+                end
+            );
+            out.insert_str(line, &header);
+            end
+        }
+    }
+}
+
+fn add(chart: &mut [Vec<Item>], seen: &mut [HashSet<Item>], pos: usize, item: Item) {
+    if seen[pos].insert(item) {
+        chart[pos].push(item);
+    }
+}
+
+fn predict(
+    rules: &RuleSet,
+    message: &[u8],
+    rule: usize,
+    pos: usize,
+    chart: &mut [Vec<Item>],
+    seen: &mut [HashSet<Item>],
+) {
+    let item = |alternative| Item {
+        rule,
+        alternative,
+        dot: 0,
+        origin: pos,
+    };
+    match rules.rules.get(&rule) {
+        // scan right away, landing complete where the literal ends
+        Some(Body::Literal(literal)) if message[pos..].starts_with(literal.as_bytes()) => {
+            add(chart, seen, pos + literal.len(), item(0));
+        }
+        Some(Body::Alternatives(alternatives)) => {
+            for alternative in 0..alternatives.len() {
+                add(chart, seen, pos, item(alternative));
+            }
+        }
+        _ => (),
+    }
+}
+
+fn complete(
+    item: Item,
+    pos: usize,
+    chart: &mut [Vec<Item>],
+    seen: &mut [HashSet<Item>],
+    spans: &mut HashSet<(usize, usize, usize)>,
+    rules: &RuleSet,
+) {
+    if !spans.insert((item.rule, item.origin, pos)) {
+        return;
+    }
+    // advance everything at the origin that was waiting on this rule
+    let mut i = 0;
+    while i < chart[item.origin].len() {
+        let waiting = chart[item.origin][i];
+        i += 1;
+        if let Some(Body::Alternatives(alternatives)) = rules.rules.get(&waiting.rule) {
+            if alternatives[waiting.alternative].get(waiting.dot) == Some(&item.rule) {
+                let advanced = Item {
+                    dot: waiting.dot + 1,
+                    ..waiting
+                };
+                add(chart, seen, pos, advanced);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+fn test_rules(lines: &[&str]) -> RuleSet {
+    let mut rules = RuleSet::default();
+    for line in lines {
+        rules.insert_line(line).unwrap();
+    }
+    rules
+}
+
+#[test]
+fn left_recursion() {
+    let rules = test_rules(&["0: 0 1 | 1", "1: \"a\""]);
+    for message in &["a", "aa", "aaaaa"] {
+        let mut parse = Parse::new(&rules, 0, message);
+        assert!(parse.matches());
+        assert_eq!(parse.count(), Count::Finite(1));
+    }
+    assert!(!Parse::new(&rules, 0, "").matches());
+    assert!(!Parse::new(&rules, 0, "ab").matches());
+}
+
+#[test]
+fn nullable_rules() {
+    // 1 is an optional "a", 4 the empty literal
+    let rules = test_rules(&["0: 1 2 1 4", "1: 3 |", "2: \"b\"", "3: \"a\"", "4: \"\""]);
+    for message in &["b", "ab", "ba", "aba"] {
+        let mut parse = Parse::new(&rules, 0, message);
+        assert!(parse.matches(), "{}", message);
+        assert_eq!(parse.count(), Count::Finite(1));
+    }
+    for message in &["", "a", "aab", "bb"] {
+        assert!(!Parse::new(&rules, 0, message).matches(), "{}", message);
+    }
+}
+
+#[test]
+fn cycle_is_infinite() {
+    let rules = test_rules(&["0: 2 | 1", "1: \"a\"", "2: 0"]);
+    let mut parse = Parse::new(&rules, 0, "a");
+    assert!(parse.matches());
+    assert_eq!(parse.count(), Count::Infinite);
+    // the trees that don't go round the cycle are still there
+    assert_eq!(parse.trees(10).len(), 1);
+}
+
+#[test]
+fn ambiguous_count() {
+    // every way of bracketing n letters, the Catalan number C(n - 1)
+    let rules = test_rules(&["0: 0 0 | 1", "1: \"a\""]);
+    for (message, catalan) in &[("a", 1), ("aaa", 2), ("aaaa", 5), ("aaaaaa", 42)] {
+        let mut parse = Parse::new(&rules, 0, message);
+        assert_eq!(parse.count(), Count::Finite(*catalan));
+        assert_eq!(parse.trees(100).len(), *catalan as usize);
+    }
+}
@@ -0,0 +1,266 @@
+use crate::dfa::{self, Dfa};
+use crate::rules::{Body, ParseError, RuleSet};
+use rand::Rng;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+
+/// Enumerates, samples and counts the messages a rule accepts, up to a maximum length.
+///
+/// Non-recursive rules go through a DFA, so counts are of distinct messages and sampling is
+/// uniform over them. Recursive rules work on the grammar directly, where counts are of
+/// derivations and sampling is uniform over derivations; the two agree unless the rules
+/// are ambiguous.
+pub enum Generator<'a> {
+    Regular(Dfa),
+    Recursive(Derivations<'a>),
+}
+
+impl<'a> Generator<'a> {
+    pub fn new(rules: &'a RuleSet, id: usize, max_len: usize) -> Result<Self, Box<dyn Error>> {
+        if dfa::check_acyclic(rules, id).is_ok() {
+            Ok(Generator::Regular(Dfa::compile(rules, id)?))
+        } else {
+            Ok(Generator::Recursive(Derivations::new(rules, id, max_len)?))
+        }
+    }
+
+    /// Whether `counts` are of distinct messages rather than derivations.
+    pub fn exact(&self) -> bool {
+        matches!(self, Generator::Regular(_))
+    }
+
+    pub fn counts(&self, max_len: usize) -> Vec<u128> {
+        match self {
+            Generator::Regular(dfa) => dfa.count_by_length(max_len),
+            Generator::Recursive(derivations) => {
+                (0..=max_len).map(|len| derivations.count(len)).collect()
+            }
+        }
+    }
+
+    /// Up to `limit` distinct messages of length `len`; lexicographic for non-recursive rules.
+    pub fn strings(&self, len: usize, limit: usize) -> Vec<String> {
+        match self {
+            Generator::Regular(dfa) => dfa.strings(len, limit),
+            Generator::Recursive(derivations) => derivations.strings(len, limit),
+        }
+    }
+
+    pub fn sample<R: Rng>(&self, len: usize, rng: &mut R) -> Option<String> {
+        match self {
+            Generator::Regular(dfa) => dfa.sample(len, rng),
+            Generator::Recursive(derivations) => derivations.sample(len, rng),
+        }
+    }
+}
+
+/// Derivation counts per rule and length, for grammars that may recurse.
+pub struct Derivations<'a> {
+    rules: &'a RuleSet,
+    start: usize,
+    // ways[rule][len]
+    ways: HashMap<usize, Vec<u128>>,
+}
+
+impl<'a> Derivations<'a> {
+    /// Fails if some rule derives itself without consuming input, which makes for
+    /// infinitely many derivations.
+    pub fn new(rules: &'a RuleSet, start: usize, max_len: usize) -> Result<Self, Box<dyn Error>> {
+        let mut derivations = Derivations {
+            rules,
+            start,
+            ways: rules
+                .rules
+                .keys()
+                .map(|&id| (id, vec![0; max_len + 1]))
+                .collect(),
+        };
+        for len in 0..=max_len {
+            // same-length dependencies only come from rules that can match nothing or a
+            // single other rule, so this settles within one round per rule unless they loop
+            let mut settled = false;
+            for _ in 0..=rules.rules.len() {
+                let mut changed = false;
+                for (&id, body) in &rules.rules {
+                    let ways = match body {
+                        Body::Literal(literal) => (literal.len() == len) as u128,
+                        Body::Alternatives(alternatives) => alternatives
+                            .iter()
+                            .map(|sequence| derivations.sequence(sequence, len).pop().unwrap())
+                            .fold(0, u128::saturating_add),
+                    };
+                    let slot = &mut derivations.ways.get_mut(&id).unwrap()[len];
+                    if *slot != ways {
+                        *slot = ways;
+                        changed = true;
+                    }
+                }
+                if !changed {
+                    settled = true;
+                    break;
+                }
+            }
+            if !settled {
+                return Err(Box::new(ParseError(format!(
+                    "rules derive themselves without consuming input at length {}",
+                    len
+                ))));
+            }
+        }
+        Ok(derivations)
+    }
+
+    pub fn count(&self, len: usize) -> u128 {
+        self.ways[&self.start].get(len).copied().unwrap_or(0)
+    }
+
+    // prefix[j]: ways for the whole sequence to cover exactly j bytes, for j up to len
+    fn sequence(&self, sequence: &[usize], len: usize) -> Vec<u128> {
+        let mut prefix = vec![0u128; len + 1];
+        prefix[0] = 1;
+        for rule in sequence {
+            let ways = &self.ways[rule];
+            let mut next = vec![0u128; len + 1];
+            for (j, &before) in prefix.iter().enumerate().filter(|(_, &w)| w != 0) {
+                for m in 0..=len - j {
+                    let product = before.saturating_mul(ways[m]);
+                    next[j + m] = next[j + m].saturating_add(product);
+                }
+            }
+            prefix = next;
+        }
+        prefix
+    }
+
+    pub fn sample<R: Rng>(&self, len: usize, rng: &mut R) -> Option<String> {
+        if self.count(len) == 0 {
+            return None;
+        }
+        let mut out = String::new();
+        self.sample_rule(self.start, len, rng, &mut out);
+        Some(out)
+    }
+
+    fn sample_rule<R: Rng>(&self, id: usize, len: usize, rng: &mut R, out: &mut String) {
+        let alternatives = match &self.rules.rules[&id] {
+            Body::Literal(literal) => {
+                out.push_str(literal);
+                return;
+            }
+            Body::Alternatives(alternatives) => alternatives,
+        };
+        let weights: Vec<u128> = alternatives
+            .iter()
+            .map(|sequence| self.sequence(sequence, len)[len])
+            .collect();
+        let sequence = &alternatives[pick(&weights, rng)];
+        // choose each rule's length from the back, weighted by the ways to fill the rest
+        let mut lengths = vec![0; sequence.len()];
+        let mut remaining = len;
+        for i in (0..sequence.len()).rev() {
+            let before = self.sequence(&sequence[..i], remaining);
+            let ways = &self.ways[&sequence[i]];
+            let weights: Vec<u128> = (0..=remaining)
+                .map(|m| before[remaining - m].saturating_mul(ways[m]))
+                .collect();
+            lengths[i] = pick(&weights, rng);
+            remaining -= lengths[i];
+        }
+        for (&rule, &len) in sequence.iter().zip(&lengths) {
+            self.sample_rule(rule, len, rng, out);
+        }
+    }
+
+    /// Up to `limit` distinct messages of length `len`, in the order their derivations
+    /// are first found.
+    pub fn strings(&self, len: usize, limit: usize) -> Vec<String> {
+        let mut found = Vec::new();
+        let mut seen = HashSet::new();
+        if limit > 0 && self.count(len) > 0 {
+            let mut prefix = String::new();
+            let todo = vec![Pending::Rule(self.start, len)];
+            self.walk(todo, &mut prefix, &mut found, &mut seen, limit);
+        }
+        found
+    }
+
+    // expands the leftmost pending item, only following choices that can still fill their
+    // lengths, so every branch ends in a message; returns true once `limit` are found
+    fn walk(
+        &self,
+        mut todo: Vec<Pending<'a>>,
+        prefix: &mut String,
+        found: &mut Vec<String>,
+        seen: &mut HashSet<String>,
+        limit: usize,
+    ) -> bool {
+        let next = match todo.pop() {
+            Some(next) => next,
+            None => {
+                if seen.insert(prefix.clone()) {
+                    found.push(prefix.clone());
+                }
+                return found.len() >= limit;
+            }
+        };
+        match next {
+            Pending::Rule(id, len) => match &self.rules.rules[&id] {
+                Body::Literal(literal) => {
+                    let mark = prefix.len();
+                    prefix.push_str(literal);
+                    let done = self.walk(todo, prefix, found, seen, limit);
+                    prefix.truncate(mark);
+                    done
+                }
+                Body::Alternatives(alternatives) => {
+                    for sequence in alternatives {
+                        if self.sequence(sequence, len)[len] == 0 {
+                            continue;
+                        }
+                        let mut todo = todo.clone();
+                        todo.push(Pending::Sequence(sequence, len));
+                        if self.walk(todo, prefix, found, seen, limit) {
+                            return true;
+                        }
+                    }
+                    false
+                }
+            },
+            Pending::Sequence([], _) => self.walk(todo, prefix, found, seen, limit),
+            Pending::Sequence([first, rest @ ..], len) => {
+                for m in 0..=len {
+                    if self.ways[first][m] == 0 || self.sequence(rest, len - m)[len - m] == 0 {
+                        continue;
+                    }
+                    let mut todo = todo.clone();
+                    todo.push(Pending::Sequence(rest, len - m));
+                    todo.push(Pending::Rule(*first, m));
+                    if self.walk(todo, prefix, found, seen, limit) {
+                        return true;
+                    }
+                }
+                false
+            }
+        }
+    }
+}
+
+// work left for `walk`, innermost last
+#[derive(Clone, Copy)]
+enum Pending<'a> {
+    Rule(usize, usize),
+    Sequence(&'a [usize], usize),
+}
+
+// index chosen with probability proportional to its weight
+fn pick<R: Rng>(weights: &[u128], rng: &mut R) -> usize {
+    let total = weights.iter().fold(0u128, |a, &w| a.saturating_add(w));
+    let mut n = rng.gen_range(0..total);
+    for (i, &w) in weights.iter().enumerate() {
+        if n < w {
+            return i;
+        }
+        n -= w;
+    }
+    unreachable!()
+}
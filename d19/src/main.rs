@@ -1,23 +1,169 @@
-#[macro_use]
-extern crate pest_derive;
-
-use pest::Parser;
 use std::error::Error;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+mod dfa;
+mod earley;
+mod generate;
+mod rules;
+use dfa::Dfa;
+use earley::Parse;
+use generate::Generator;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rules::RuleSet;
 
-#[derive(Parser)]
-#[grammar = "rules.pest"]
-struct RuleParser;
+// The puzzle's part 2 replacements.
+const LOOPED_RULES: [&str; 2] = ["8: 42 | 42 8", "11: 42 31 | 42 11 31"];
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let filename = std::env::args().nth(1).unwrap();
+    let mut args = std::env::args().skip(1);
+    let filename = args.next().unwrap();
+    let mut replacements: Vec<String> = vec![];
+    let mut backtrack = false;
+    let mut count = false;
+    let mut trees = 0;
+    let mut use_dfa = false;
+    let mut explain = false;
+    let mut print_regex = false;
+    let mut generate: Option<(String, usize, usize)> = None;
+    let mut seed = 0;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            // the older matcher, fine unless rules are left-recursive or can match nothing
+            "--backtrack" => backtrack = true,
+            "--count" => count = true,
+            // compile rule 0 to a DFA first, it must not be recursive
+            "--dfa" => use_dfa = true,
+            "--explain" => explain = true,
+            "--regex" => print_regex = true,
+            // --lengths MAX, --enumerate LEN LIMIT, --sample LEN N, --fuzz LEN N
+            "--lengths" | "--enumerate" | "--sample" | "--fuzz" => {
+                let len = args.next().unwrap().parse()?;
+                let n = match arg.as_str() {
+                    "--lengths" => 0,
+                    _ => args.next().unwrap().parse()?,
+                };
+                generate = Some((arg, len, n));
+            }
+            "--seed" => seed = args.next().unwrap().parse()?,
+            // --trees N, print up to N parse trees per message
+            "--trees" => trees = args.next().unwrap().parse()?,
+            "--loop" => replacements.extend(LOOPED_RULES.iter().map(|r| r.to_string())),
+            // --replace "8: 42 | 42 8"
+            "--replace" => replacements.push(args.next().unwrap()),
+            _ => return Err(format!("unknown argument {:?}", arg).into()),
+        }
+    }
     let input = File::open(filename)?;
     let buffered = BufReader::new(input);
-    let v: usize = buffered
-        .lines()
-        .filter(|r| (RuleParser::parse(Rule::r0, &r.as_ref().unwrap())).is_ok())
-        .count();
+    let mut lines = buffered.lines().map(Result::unwrap);
+    let mut rules = RuleSet::default();
+    for line in lines.by_ref().take_while(|line| !line.trim().is_empty()) {
+        rules.insert_line(&line)?;
+    }
+    for line in &replacements {
+        rules.insert_line(line)?;
+    }
+    let undefined = rules.undefined();
+    if !undefined.is_empty() {
+        return Err(format!("rules {:?} are used but not defined", undefined).into());
+    }
+    if !rules.rules.contains_key(&0) {
+        return Err("rule 0 is not defined".into());
+    }
+    if let Some((mode, len, n)) = generate {
+        let generator = Generator::new(&rules, 0, len)?;
+        let mut rng = StdRng::seed_from_u64(seed);
+        match mode.as_str() {
+            "--lengths" => {
+                let what = if generator.exact() {
+                    "messages"
+                } else {
+                    "derivations"
+                };
+                for (len, count) in generator.counts(len).into_iter().enumerate() {
+                    println!("{}\t{} {}", len, count, what);
+                }
+            }
+            "--enumerate" => {
+                for message in generator.strings(len, n) {
+                    println!("{}", message);
+                }
+            }
+            "--sample" => {
+                for _ in 0..n {
+                    match generator.sample(len, &mut rng) {
+                        Some(message) => println!("{}", message),
+                        None => break,
+                    }
+                }
+            }
+            _ => {
+                // every sample must be accepted by every matcher that can handle the rules
+                let dfa = Dfa::compile(&rules, 0).ok();
+                let backtrack = rules.check_backtracking(0).is_ok();
+                let mut failures = 0;
+                for _ in 0..n {
+                    let message = match generator.sample(len, &mut rng) {
+                        Some(message) => message,
+                        None => break,
+                    };
+                    let earley = Parse::new(&rules, 0, &message).matches();
+                    let backtracking = !backtrack || rules.matches(0, &message);
+                    let dfa = dfa.as_ref().is_none_or(|dfa| dfa.matches(&message));
+                    if !(earley && backtracking && dfa) {
+                        failures += 1;
+                        println!(
+                            "{}: earley {} backtracking {} dfa {}",
+                            message, earley, backtracking, dfa
+                        );
+                    }
+                }
+                dbg!(failures);
+            }
+        }
+        return Ok(());
+    }
+    if print_regex {
+        println!("{}", dfa::regex(&rules, 0)?);
+        return Ok(());
+    }
+    let dfa = if use_dfa {
+        let dfa = Dfa::compile(&rules, 0)?;
+        dbg!(dfa.states());
+        Some(dfa)
+    } else {
+        None
+    };
+    if backtrack {
+        rules.check_backtracking(0)?;
+    }
+    let mut v = 0;
+    for line in lines {
+        let message = line.trim();
+        if let Some(dfa) = &dfa {
+            v += dfa.matches(message) as usize;
+            continue;
+        }
+        if backtrack {
+            v += rules.matches(0, message) as usize;
+            continue;
+        }
+        let mut parse = Parse::new(&rules, 0, message);
+        if explain {
+            print!("{}", parse.explain());
+        }
+        if !parse.matches() {
+            continue;
+        }
+        v += 1;
+        if count {
+            println!("{}: {}", message, parse.count());
+        }
+        for tree in parse.trees(trees) {
+            println!("{}", tree);
+        }
+    }
     dbg!(v);
     Ok(())
 }
@@ -0,0 +1,185 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Body {
+    /// `"a"`
+    Literal(String),
+    /// `1 2 | 3 4`, any of the sequences of rule numbers
+    Alternatives(Vec<Vec<usize>>),
+}
+
+/// Numbered message rules in the puzzle's `N: a b | c d` / `N: "a"` format.
+#[derive(Debug, Clone, Default)]
+pub struct RuleSet {
+    pub rules: HashMap<usize, Body>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ParseError(pub String);
+impl Error for ParseError {}
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl RuleSet {
+    /// Adds a rule line, replacing any existing rule with the same number.
+    pub fn insert_line(&mut self, line: &str) -> Result<(), Box<dyn Error>> {
+        let mut parts = line.splitn(2, ':');
+        let id: usize = parts.next().unwrap().trim().parse()?;
+        let body = parts
+            .next()
+            .ok_or_else(|| ParseError(format!("missing `:` in rule {:?}", line)))?
+            .parse()?;
+        self.rules.insert(id, body);
+        Ok(())
+    }
+
+    fn get(&self, id: usize) -> &Body {
+        match self.rules.get(&id) {
+            Some(body) => body,
+            None => panic!("rule {} is not defined", id),
+        }
+    }
+
+    /// Rule numbers referenced but never defined.
+    pub fn undefined(&self) -> Vec<usize> {
+        let mut undefined: Vec<usize> = self
+            .rules
+            .values()
+            .flat_map(|body| match body {
+                Body::Literal(_) => vec![],
+                Body::Alternatives(alternatives) => alternatives.concat(),
+            })
+            .filter(|id| !self.rules.contains_key(id))
+            .collect();
+        undefined.sort_unstable();
+        undefined.dedup();
+        undefined
+    }
+
+    /// Rules that can match the empty string.
+    pub fn nullable(&self) -> HashSet<usize> {
+        let mut nullable = HashSet::new();
+        loop {
+            let before = nullable.len();
+            for (&id, body) in &self.rules {
+                let empty = match body {
+                    Body::Literal(literal) => literal.is_empty(),
+                    Body::Alternatives(alternatives) => alternatives
+                        .iter()
+                        .any(|sequence| sequence.iter().all(|r| nullable.contains(r))),
+                };
+                if empty {
+                    nullable.insert(id);
+                }
+            }
+            if nullable.len() == before {
+                return nullable;
+            }
+        }
+    }
+
+    /// Fails if `matches` can't be trusted with rule `id`: some rule it uses can match
+    /// nothing, or can start with itself without consuming input.
+    pub fn check_backtracking(&self, id: usize) -> Result<(), Box<dyn Error>> {
+        let nullable = self.nullable();
+        let mut reachable = HashSet::new();
+        let mut stack = vec![id];
+        while let Some(rule) = stack.pop() {
+            if !reachable.insert(rule) {
+                continue;
+            }
+            if nullable.contains(&rule) {
+                return Err(Box::new(ParseError(format!("rule {} can match nothing", rule))));
+            }
+            if let Some(Body::Alternatives(alternatives)) = self.rules.get(&rule) {
+                stack.extend(alternatives.iter().flatten());
+            }
+        }
+        // with nothing nullable, only the first rule of each alternative is tried in place
+        for &rule in &reachable {
+            let mut seen = HashSet::new();
+            let mut stack = vec![rule];
+            while let Some(current) = stack.pop() {
+                if let Some(Body::Alternatives(alternatives)) = self.rules.get(&current) {
+                    for &first in alternatives.iter().filter_map(|sequence| sequence.first()) {
+                        if first == rule {
+                            return Err(Box::new(ParseError(format!(
+                                "rule {} is left-recursive",
+                                rule
+                            ))));
+                        }
+                        if seen.insert(first) {
+                            stack.push(first);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether all of `message` matches rule `id`.
+    ///
+    /// Tracks every position a rule could end at, so rules that loop back on themselves work
+    /// as long as they consume input before recursing.
+    pub fn matches(&self, id: usize, message: &str) -> bool {
+        self.ends(id, message.as_bytes(), 0)
+            .contains(&message.len())
+    }
+
+    fn ends(&self, id: usize, message: &[u8], start: usize) -> Vec<usize> {
+        match self.get(id) {
+            Body::Literal(literal) => {
+                if message[start..].starts_with(literal.as_bytes()) {
+                    vec![start + literal.len()]
+                } else {
+                    vec![]
+                }
+            }
+            Body::Alternatives(alternatives) => {
+                let mut ends = vec![];
+                for sequence in alternatives {
+                    let mut positions = vec![start];
+                    for &rule in sequence {
+                        let mut next: Vec<usize> = positions
+                            .iter()
+                            .filter(|&&p| p < message.len())
+                            .flat_map(|&p| self.ends(rule, message, p))
+                            .collect();
+                        next.sort_unstable();
+                        next.dedup();
+                        positions = next;
+                    }
+                    ends.extend(positions);
+                }
+                ends.sort_unstable();
+                ends.dedup();
+                ends
+            }
+        }
+    }
+}
+
+impl FromStr for Body {
+    type Err = Box<dyn Error>;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(quoted) = s.strip_prefix('"') {
+            let literal = quoted
+                .strip_suffix('"')
+                .ok_or_else(|| ParseError(format!("unterminated literal {:?}", s)))?;
+            return Ok(Body::Literal(literal.to_string()));
+        }
+        let alternatives: Result<Vec<Vec<usize>>, _> = s
+            .split('|')
+            .map(|sequence| sequence.split_whitespace().map(str::parse).collect())
+            .collect();
+        Ok(Body::Alternatives(alternatives?))
+    }
+}
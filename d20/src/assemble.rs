@@ -0,0 +1,219 @@
+use crate::edges;
+use crate::{Tile, Transform};
+use ndarray::Array1;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// How the assembler looks up tiles by edge.
+pub trait EdgeKey: Hash + Eq + Clone {
+    /// Top, right, bottom and left edges for each transform in `Transform::all` order.
+    fn sides(tile: &Tile) -> Vec<[Self; 4]>;
+}
+
+// the cells themselves, read off each transformed copy of the tile
+impl EdgeKey for Array1<u8> {
+    fn sides(tile: &Tile) -> Vec<[Self; 4]> {
+        Transform::all()
+            .map(|t| {
+                let tile = tile.transformed(t);
+                [
+                    tile.top().to_owned(),
+                    tile.right().to_owned(),
+                    tile.bottom().to_owned(),
+                    tile.left().to_owned(),
+                ]
+            })
+            .collect()
+    }
+}
+
+// the cells packed into bitmasks, worked out from the four original edges
+impl EdgeKey for u64 {
+    fn sides(tile: &Tile) -> Vec<[Self; 4]> {
+        edges::sides(tile).to_vec()
+    }
+}
+
+/// Tiles laid out row by row, each already transformed into place.
+#[derive(Debug, Clone)]
+pub struct Assembly {
+    pub rows: usize,
+    pub cols: usize,
+    pub tiles: Vec<Tile>,
+    pub transforms: Vec<Transform>,
+}
+
+impl Assembly {
+    pub fn get(&self, row: usize, col: usize) -> &Tile {
+        &self.tiles[row * self.cols + col]
+    }
+
+    pub fn grid(&self) -> Vec<Vec<Tile>> {
+        self.tiles.chunks(self.cols).map(<[Tile]>::to_vec).collect()
+    }
+
+    pub fn ids(&self) -> Vec<Vec<u64>> {
+        self.tiles
+            .chunks(self.cols)
+            .map(|row| row.iter().map(|tile| tile.id).collect())
+            .collect()
+    }
+
+    pub fn corner_ids(&self) -> [u64; 4] {
+        let (last_row, last_col) = (self.rows - 1, self.cols - 1);
+        [
+            self.get(0, 0).id,
+            self.get(0, last_col).id,
+            self.get(last_row, 0).id,
+            self.get(last_row, last_col).id,
+        ]
+    }
+}
+
+// the edges of every orientation of every tile, indexed by left and top edge
+struct Orientations<K> {
+    sides: Vec<Vec<[K; 4]>>,
+    by_left: HashMap<K, Vec<(usize, usize)>>,
+    by_top: HashMap<K, Vec<(usize, usize)>>,
+}
+
+const TOP: usize = 0;
+const RIGHT: usize = 1;
+const BOTTOM: usize = 2;
+const LEFT: usize = 3;
+
+impl<K: EdgeKey> Orientations<K> {
+    fn new(tiles: &[Tile]) -> Self {
+        let sides: Vec<Vec<[K; 4]>> = tiles.iter().map(K::sides).collect();
+        let mut by_left: HashMap<K, Vec<(usize, usize)>> = HashMap::new();
+        let mut by_top: HashMap<K, Vec<(usize, usize)>> = HashMap::new();
+        for (i, orientations) in sides.iter().enumerate() {
+            for (t, edges) in orientations.iter().enumerate() {
+                by_left.entry(edges[LEFT].clone()).or_default().push((i, t));
+                by_top.entry(edges[TOP].clone()).or_default().push((i, t));
+            }
+        }
+        Orientations {
+            sides,
+            by_left,
+            by_top,
+        }
+    }
+
+    // every edge turns up as the top of some orientation, in both directions
+    fn shared(&self, edge: &K, tile: usize) -> bool {
+        self.by_top
+            .get(edge)
+            .is_some_and(|found| found.iter().any(|&(other, _)| other != tile))
+    }
+
+    // candidates for the next position, in the order they should be tried
+    fn candidates(&self, placed: &[(usize, usize)], cols: usize) -> Vec<(usize, usize)> {
+        let pos = placed.len();
+        let (row, col) = (pos / cols, pos % cols);
+        let side = |(i, t): (usize, usize), side: usize| &self.sides[i][t][side];
+        let above = if row > 0 {
+            Some(side(placed[pos - cols], BOTTOM))
+        } else {
+            None
+        };
+        if col > 0 {
+            let right = side(placed[pos - 1], RIGHT);
+            let mut candidates = self.by_left.get(right).cloned().unwrap_or_default();
+            if let Some(above) = above {
+                candidates.retain(|&c| side(c, TOP) == above);
+            }
+            candidates
+        } else if let Some(above) = above {
+            self.by_top.get(above).cloned().unwrap_or_default()
+        } else {
+            // any orientation can start the grid, but the ones with nothing above or to the
+            // left of them are far more likely to be the real top left corner
+            let mut candidates: Vec<(usize, usize)> = (0..self.sides.len())
+                .flat_map(|i| (0..8).map(move |t| (i, t)))
+                .collect();
+            candidates.sort_by_key(|&(i, t)| {
+                let edges = &self.sides[i][t];
+                self.shared(&edges[TOP], i) as usize + self.shared(&edges[LEFT], i) as usize
+            });
+            candidates
+        }
+    }
+}
+
+/// Arranges the tiles into a rectangle where every pair of neighbouring edges agree,
+/// trying the squarest grid shapes first. Edges are compared as bitmasks unless the tiles
+/// are too wide for them.
+pub fn assemble(tiles: &[Tile]) -> Option<Assembly> {
+    if tiles.iter().any(|tile| tile.data.ncols() > edges::MAX_LEN) {
+        assemble_with::<Array1<u8>>(tiles)
+    } else {
+        assemble_with::<u64>(tiles)
+    }
+}
+
+pub fn assemble_with<K: EdgeKey>(tiles: &[Tile]) -> Option<Assembly> {
+    let orientations = Orientations::<K>::new(tiles);
+    grid_shapes(tiles.len())
+        .into_iter()
+        .find_map(|(rows, cols)| assemble_grid(tiles, &orientations, rows, cols))
+}
+
+// (rows, cols) with rows * cols == n, squarest first
+fn grid_shapes(n: usize) -> Vec<(usize, usize)> {
+    let mut shapes: Vec<(usize, usize)> = (1..=n)
+        .filter(|rows| n.is_multiple_of(*rows))
+        .map(|rows| (rows, n / rows))
+        .collect();
+    shapes.sort_by_key(|&(rows, cols)| (rows as isize - cols as isize).abs());
+    shapes
+}
+
+// depth first over positions in row-major order, kept on an explicit stack since large
+// puzzles go thousands of placements deep
+fn assemble_grid<K: EdgeKey>(
+    tiles: &[Tile],
+    orientations: &Orientations<K>,
+    rows: usize,
+    cols: usize,
+) -> Option<Assembly> {
+    let n = rows * cols;
+    let mut used = vec![false; n];
+    let mut placed: Vec<(usize, usize)> = Vec::with_capacity(n);
+    // untried candidates for each position up to the next one to fill, in reverse order
+    let mut remaining: Vec<Vec<(usize, usize)>> = Vec::with_capacity(n);
+    while placed.len() < n {
+        if remaining.len() == placed.len() {
+            let mut candidates = orientations.candidates(&placed, cols);
+            candidates.reverse();
+            remaining.push(candidates);
+        }
+        let next = loop {
+            match remaining.last_mut().unwrap().pop() {
+                Some((i, _)) if used[i] => continue,
+                other => break other,
+            }
+        };
+        match next {
+            Some((i, t)) => {
+                used[i] = true;
+                placed.push((i, t));
+            }
+            None => {
+                remaining.pop();
+                let (i, _) = placed.pop()?;
+                used[i] = false;
+            }
+        }
+    }
+    let all: Vec<Transform> = Transform::all().collect();
+    Some(Assembly {
+        rows,
+        cols,
+        tiles: placed
+            .iter()
+            .map(|&(i, t)| tiles[i].transformed(all[t]))
+            .collect(),
+        transforms: placed.iter().map(|&(_, t)| all[t]).collect(),
+    })
+}
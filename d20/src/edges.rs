@@ -0,0 +1,101 @@
+use crate::Tile;
+use ndarray::ArrayView1;
+use std::collections::HashMap;
+use std::error::Error;
+
+/// The longest edge that fits in the bits of a `u64`.
+pub const MAX_LEN: usize = 64;
+
+/// The edge read as a binary number, first cell most significant and `#` as 1.
+pub fn bits(edge: ArrayView1<u8>) -> u64 {
+    assert!(edge.len() <= MAX_LEN, "edges longer than 64 cells");
+    edge.iter()
+        .fold(0, |acc, &b| (acc << 1) | (b == b'#') as u64)
+}
+
+pub fn reverse(bits: u64, len: usize) -> u64 {
+    bits.reverse_bits() >> (64 - len)
+}
+
+/// The same for an edge and its reverse, so two tiles that can be turned to meet along an
+/// edge share its canonical value.
+pub fn canonical(bits: u64, len: usize) -> u64 {
+    bits.min(reverse(bits, len))
+}
+
+/// Top, right, bottom and left edges as bits for each transform in `Transform::all` order,
+/// without transforming the tile itself.
+pub fn sides(tile: &Tile) -> [[u64; 4]; 8] {
+    let len = tile.data.ncols();
+    let rev = |bits| reverse(bits, len);
+    // read clockwise round the tile, so a quarter turn just moves each edge along one
+    let clockwise = [
+        bits(tile.top()),
+        bits(tile.right()),
+        rev(bits(tile.bottom())),
+        rev(bits(tile.left())),
+    ];
+    // a mirror image reads the other way round, starting from the same top edge
+    let flipped = [
+        rev(clockwise[0]),
+        rev(clockwise[3]),
+        rev(clockwise[2]),
+        rev(clockwise[1]),
+    ];
+    let mut sides = [[0; 4]; 8];
+    for (i, edges) in [clockwise, flipped].iter().enumerate() {
+        for rotations in 0..4 {
+            let turned = |side: usize| edges[(side + 4 - rotations) % 4];
+            sides[i * 4 + rotations] = [turned(0), turned(1), rev(turned(2)), rev(turned(3))];
+        }
+    }
+    sides
+}
+
+/// Tiles by the canonical values of their four edges.
+pub struct EdgeIndex {
+    pub tiles_by_edge: HashMap<u64, Vec<usize>>,
+    len: usize,
+}
+
+impl EdgeIndex {
+    /// Fails if the tiles are too wide for their edges to fit in bits.
+    pub fn new(tiles: &[Tile]) -> Result<Self, Box<dyn Error>> {
+        let len = tiles.first().map_or(0, |tile| tile.data.ncols());
+        if len > MAX_LEN {
+            return Err(format!("edges of {} cells don't fit in {} bits", len, MAX_LEN).into());
+        }
+        let mut tiles_by_edge: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (i, tile) in tiles.iter().enumerate() {
+            for edge in tile.edges() {
+                tiles_by_edge
+                    .entry(canonical(bits(edge), len))
+                    .or_default()
+                    .push(i);
+            }
+        }
+        Ok(EdgeIndex { tiles_by_edge, len })
+    }
+
+    /// Whether any tile other than `tile` has this edge, given as bits in either direction.
+    pub fn shared(&self, edge: u64, tile: usize) -> bool {
+        self.tiles_by_edge
+            .get(&canonical(edge, self.len))
+            .is_some_and(|tiles| tiles.iter().any(|&other| other != tile))
+    }
+
+    /// Tiles with exactly two edges that no other tile has, which are the corners as long
+    /// as inner edges are unique.
+    pub fn corners(&self, tiles: &[Tile]) -> Vec<usize> {
+        (0..tiles.len())
+            .filter(|&i| {
+                tiles[i]
+                    .edges()
+                    .into_iter()
+                    .filter(|&edge| !self.shared(bits(edge), i))
+                    .count()
+                    == 2
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,203 @@
+use crate::assemble::Assembly;
+use crate::{write_tiles, Tile};
+use ndarray::Array2;
+use std::error::Error;
+use std::io::{self, BufWriter, Write};
+
+// 3x5 digits, read row by row from the top bit
+const DIGITS: [u16; 10] = [
+    0b111_101_101_101_111,
+    0b010_110_010_010_111,
+    0b111_001_111_100_111,
+    0b111_001_111_001_111,
+    0b101_101_111_001_001,
+    0b111_100_111_001_111,
+    0b111_100_111_101_111,
+    0b111_001_010_010_010,
+    0b111_101_111_101_111,
+    0b111_101_111_001_111,
+];
+const LABEL_HEIGHT: usize = 7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ink {
+    Empty,
+    Filled,
+    Grid,
+    Label,
+    LabelBackground,
+}
+
+impl Ink {
+    fn rgb(self) -> [u8; 3] {
+        match self {
+            Ink::Empty => [30, 70, 140],
+            Ink::Filled => [235, 225, 190],
+            Ink::Grid => [120, 120, 120],
+            Ink::Label => [0, 0, 0],
+            Ink::LabelBackground => [255, 255, 255],
+        }
+    }
+
+    fn black(self) -> bool {
+        matches!(self, Ink::Filled | Ink::Grid | Ink::Label)
+    }
+}
+
+/// The placed tiles as they are, or with their borders cut off.
+pub fn blocks(assembly: &Assembly, borders: bool) -> Vec<Vec<Array2<u8>>> {
+    assembly
+        .grid()
+        .into_iter()
+        .map(|row| {
+            row.into_iter()
+                .map(|tile| {
+                    let (nrows, ncols) = tile.data.dim();
+                    if borders {
+                        tile.data
+                    } else {
+                        tile.data.slice(s![1..nrows - 1, 1..ncols - 1]).to_owned()
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// The blocks side by side. Without borders this is the picture the puzzle is about.
+pub fn merge(blocks: &[Vec<Array2<u8>>]) -> Array2<u8> {
+    let (nrows, ncols) = blocks[0][0].dim();
+    let mut image = Array2::from_elem((nrows * blocks.len(), ncols * blocks[0].len()), b'.');
+    for (i, row) in blocks.iter().enumerate() {
+        for (j, block) in row.iter().enumerate() {
+            image
+                .slice_mut(s![i * nrows..(i + 1) * nrows, j * ncols..(j + 1) * ncols])
+                .assign(block);
+        }
+    }
+    image
+}
+
+/// Every cell as a `scale` by `scale` square. With `ids`, blocks are boxed in by grid lines
+/// and each gets its tile id written in the strip above it.
+pub fn raster(blocks: &[Vec<Array2<u8>>], ids: Option<&[Vec<u64>]>, scale: usize) -> Array2<Ink> {
+    let (nrows, ncols) = blocks[0][0].dim();
+    let (height, width) = (nrows * scale, ncols * scale);
+    let (line, label) = if ids.is_some() {
+        (1, LABEL_HEIGHT)
+    } else {
+        (0, 0)
+    };
+    let mut canvas = Array2::from_elem(
+        (
+            blocks.len() * (label + height + line) + line,
+            blocks[0].len() * (width + line) + line,
+        ),
+        Ink::Grid,
+    );
+    for (i, row) in blocks.iter().enumerate() {
+        for (j, block) in row.iter().enumerate() {
+            let (y, x) = (
+                line + i * (label + height + line),
+                line + j * (width + line),
+            );
+            if let Some(ids) = ids {
+                canvas
+                    .slice_mut(s![y..y + label, x..x + width])
+                    .fill(Ink::LabelBackground);
+                draw_number(&mut canvas, ids[i][j], y + 1, x + 1, x + width);
+            }
+            for ((r, c), &b) in block.indexed_iter() {
+                let ink = if b == b'#' { Ink::Filled } else { Ink::Empty };
+                let (top, left) = (y + label + r * scale, x + c * scale);
+                canvas
+                    .slice_mut(s![top..top + scale, left..left + scale])
+                    .fill(ink);
+            }
+        }
+    }
+    canvas
+}
+
+// digits one column apart, cut off at `right`
+fn draw_number(canvas: &mut Array2<Ink>, n: u64, top: usize, left: usize, right: usize) {
+    for (k, digit) in n.to_string().bytes().enumerate() {
+        let glyph = DIGITS[(digit - b'0') as usize];
+        for bit in 0..15 {
+            let (r, c) = (top + bit / 3, left + k * 4 + bit % 3);
+            if c < right && glyph & (1 << (14 - bit)) != 0 {
+                canvas[[r, c]] = Ink::Label;
+            }
+        }
+    }
+}
+
+pub fn write_png<W: Write>(canvas: &Array2<Ink>, out: W) -> Result<(), Box<dyn Error>> {
+    let (height, width) = canvas.dim();
+    let mut encoder = png::Encoder::new(BufWriter::new(out), width as u32, height as u32);
+    encoder.set_color(png::ColorType::RGB);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    let data: Vec<u8> = canvas.iter().flat_map(|ink| ink.rgb()).collect();
+    writer.write_image_data(&data)?;
+    Ok(())
+}
+
+/// Plain (P1) PBM, with filled cells, grid lines and labels in black.
+pub fn write_pbm<W: Write>(canvas: &Array2<Ink>, out: W) -> io::Result<()> {
+    let mut out = BufWriter::new(out);
+    let (height, width) = canvas.dim();
+    writeln!(out, "P1\n{} {}", width, height)?;
+    for row in canvas.outer_iter() {
+        let bits: Vec<u8> = row
+            .iter()
+            .map(|ink| if ink.black() { b'1' } else { b'0' })
+            .collect();
+        // the format asks for lines of at most 70 characters
+        for chunk in bits.chunks(70) {
+            out.write_all(chunk)?;
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
+
+/// The puzzle's own format: the placed tiles in reading order with borders, which can be
+/// read back and assembled again, or the whole picture as a single tile 0 without them.
+pub fn write_text<W: Write>(assembly: &Assembly, borders: bool, out: W) -> io::Result<()> {
+    if borders {
+        write_tiles(&assembly.tiles, out)
+    } else {
+        let image = Tile {
+            id: 0,
+            data: merge(&blocks(assembly, false)),
+        };
+        write_tiles(&[image], out)
+    }
+}
+
+/// The blocks boxed in with `|`, `-` and `+`, each tile's id written into the line above it.
+pub fn overlay_text(blocks: &[Vec<Array2<u8>>], ids: &[Vec<u64>]) -> String {
+    let ncols = blocks[0][0].ncols();
+    let rule = |ids: Option<&[u64]>| {
+        let mut line = String::new();
+        for j in 0..blocks[0].len() {
+            let label = ids.map_or(String::new(), |ids| ids[j].to_string());
+            line.push('+');
+            line.extend(label.chars().chain(std::iter::repeat('-')).take(ncols));
+        }
+        line + "+\n"
+    };
+    let mut out = String::new();
+    for (row, ids) in blocks.iter().zip(ids) {
+        out += &rule(Some(ids));
+        for r in 0..row[0].nrows() {
+            for block in row {
+                out.push('|');
+                out.extend(block.row(r).iter().map(|&b| b as char));
+            }
+            out += "|\n";
+        }
+    }
+    out + &rule(None)
+}
@@ -0,0 +1,172 @@
+use crate::pattern::{self, Pattern};
+use crate::{Tile, Transform};
+use ndarray::Array2;
+use rand::seq::{index, SliceRandom};
+use rand::Rng;
+use std::collections::HashSet;
+use std::error::Error;
+
+/// A scrambled puzzle along with the picture it was cut from and its answers.
+pub struct Puzzle {
+    pub tiles: Vec<Tile>,
+    /// The picture without tile borders, as the assembler should rebuild it up to a
+    /// transform.
+    pub image: Array2<u8>,
+    pub corner_product: u64,
+    pub roughness: usize,
+}
+
+pub fn random_image<R: Rng>(nrows: usize, ncols: usize, rng: &mut R) -> Array2<u8> {
+    Array2::from_shape_fn((nrows, ncols), |_| if rng.gen() { b'#' } else { b'.' })
+}
+
+/// Stamps `count` copies of the pattern, all in one random orientation, at random places
+/// where they don't overlap each other. Gives up on a copy after enough misses.
+pub fn embed<R: Rng>(image: &mut Array2<u8>, pattern: &Pattern, count: usize, rng: &mut R) {
+    let transform = *Transform::all().collect::<Vec<_>>().choose(rng).unwrap();
+    let oriented = Tile {
+        id: 0,
+        data: pattern.data.clone(),
+    }
+    .transformed(transform)
+    .data;
+    let (nrows, ncols) = oriented.dim();
+    if nrows > image.nrows() || ncols > image.ncols() {
+        return;
+    }
+    let mut taken = Array2::from_elem(image.dim(), false);
+    for _ in 0..count {
+        for _ in 0..1000 {
+            let row = rng.gen_range(0..=image.nrows() - nrows);
+            let col = rng.gen_range(0..=image.ncols() - ncols);
+            let mut area = taken.slice_mut(s![row..row + nrows, col..col + ncols]);
+            if area.iter().any(|&t| t) {
+                continue;
+            }
+            area.fill(true);
+            for ((r, c), &b) in oriented.indexed_iter() {
+                if b == b'#' {
+                    image[[row + r, col + c]] = b'#';
+                }
+            }
+            break;
+        }
+    }
+}
+
+/// Puts a line of random cells around and between every block of `size - 2` by `size - 2`
+/// cells, giving the picture that tiles of side `size` are cut from. Each stretch of line
+/// between two crossings is redrawn until it differs from every other, backwards or
+/// forwards, so the tiles only fit together one way.
+pub fn add_borders<R: Rng>(
+    inner: &Array2<u8>,
+    size: usize,
+    rng: &mut R,
+) -> Result<Array2<u8>, Box<dyn Error>> {
+    let step = size - 1;
+    let inner_size = size - 2;
+    let (rows, cols) = (inner.nrows() / inner_size, inner.ncols() / inner_size);
+    let mut image = random_image(rows * step + 1, cols * step + 1, rng);
+    let mut used = HashSet::new();
+    // (first cell, whether it runs down rather than across) for every stretch of line
+    let across =
+        (0..=rows).flat_map(|row| (0..cols).map(move |col| ((row * step, col * step), false)));
+    let down =
+        (0..rows).flat_map(|row| (0..=cols).map(move |col| ((row * step, col * step), true)));
+    for ((r, c), vertical) in across.chain(down).collect::<Vec<_>>() {
+        let mut edge = if vertical {
+            image.slice_mut(s![r..r + size, c])
+        } else {
+            image.slice_mut(s![r, c..c + size])
+        };
+        let mut attempts = 0;
+        loop {
+            for cell in edge.iter_mut().skip(1).take(inner_size) {
+                *cell = if rng.gen() { b'#' } else { b'.' };
+            }
+            // the same whichever way round it's read
+            let cells = edge.to_vec();
+            let reversed: Vec<u8> = cells.iter().rev().copied().collect();
+            if used.insert(cells.min(reversed)) {
+                break;
+            }
+            attempts += 1;
+            if attempts == 1000 {
+                return Err("tile edges are too short to tell apart".into());
+            }
+        }
+    }
+    for row in 0..rows {
+        for col in 0..cols {
+            let (r, c) = (row * step + 1, col * step + 1);
+            let (ir, ic) = (row * inner_size, col * inner_size);
+            image
+                .slice_mut(s![r..r + inner_size, c..c + inner_size])
+                .assign(&inner.slice(s![ir..ir + inner_size, ic..ic + inner_size]));
+        }
+    }
+    Ok(image)
+}
+
+/// Cuts `rows` by `cols` square tiles out of the image, neighbours sharing the cells along
+/// their common edge, so the image needs `rows * (size - 1) + 1` rows and likewise for
+/// columns. Tiles are numbered from 1000 in reading order.
+pub fn cut(image: &Array2<u8>, rows: usize, cols: usize, size: usize) -> Vec<Tile> {
+    let step = size - 1;
+    let mut tiles = vec![];
+    for row in 0..rows {
+        for col in 0..cols {
+            let (r, c) = (row * step, col * step);
+            let data = image.slice(s![r..r + size, c..c + size]).to_owned();
+            tiles.push(Tile::from_array(1000 + tiles.len() as u64, data));
+        }
+    }
+    tiles
+}
+
+/// Turns every tile to a random orientation and shuffles them.
+pub fn scramble<R: Rng>(tiles: &mut [Tile], rng: &mut R) {
+    let all: Vec<Transform> = Transform::all().collect();
+    for tile in tiles.iter_mut() {
+        tile.transform(*all.choose(rng).unwrap());
+    }
+    tiles.shuffle(rng);
+}
+
+/// A scrambled puzzle of random noise with up to `monsters` sea monsters hidden in it.
+pub fn puzzle<R: Rng>(
+    rows: usize,
+    cols: usize,
+    size: usize,
+    monsters: usize,
+    rng: &mut R,
+) -> Result<Puzzle, Box<dyn Error>> {
+    if rows == 0 || cols == 0 {
+        return Err("puzzle has no tiles".into());
+    }
+    if size < 3 {
+        return Err("tiles need to be at least 3 cells across".into());
+    }
+    let inner_size = size - 2;
+    let mut image = random_image(rows * inner_size, cols * inner_size, rng);
+    let monster = Pattern::sea_monster();
+    embed(&mut image, &monster, monsters, rng);
+    let mut tiles = cut(&add_borders(&image, size, rng)?, rows, cols, size);
+    // four digit ids like the real puzzle, if there are few enough tiles
+    if tiles.len() <= 9000 {
+        for (tile, id) in tiles.iter_mut().zip(index::sample(rng, 9000, rows * cols)) {
+            tile.id = 1000 + id as u64;
+        }
+    }
+    let corner_product =
+        tiles[0].id * tiles[cols - 1].id * tiles[(rows - 1) * cols].id * tiles[rows * cols - 1].id;
+    let covered = monster.covered(&image, &monster.find(&image));
+    let roughness = pattern::roughness(&image, &covered);
+    scramble(&mut tiles, rng);
+    Ok(Puzzle {
+        tiles,
+        image,
+        corner_product,
+        roughness,
+    })
+}
@@ -2,151 +2,214 @@
 #[macro_use]
 extern crate ndarray;
 use ndarray::{Array1, Array2, ArrayView1};
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::error::Error;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+
+mod assemble;
+mod edges;
+mod export;
+mod generate;
+mod pattern;
+use assemble::{assemble, assemble_with};
+use edges::EdgeIndex;
+use pattern::Pattern;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::time::Instant;
+
 fn main() -> Result<(), Box<dyn Error>> {
-    let filename = std::env::args().nth(1).unwrap();
+    let mut args = std::env::args().skip(1);
+    let filename = args.next().unwrap();
+    if filename == "bench" {
+        // bench [GRID=100] [TILE SIZE=32]
+        let grid = args.next().map_or(Ok(100), |s| s.parse())?;
+        let size = args.next().map_or(Ok(32), |s| s.parse())?;
+        return bench(grid, size);
+    }
+    if filename == "generate" {
+        // generate OUT ROWS COLS [TILE SIZE=10] [MONSTERS=0] [SEED=0]
+        let out = args.next().ok_or("missing output file")?;
+        let rows = args.next().ok_or("missing rows")?.parse()?;
+        let cols = args.next().ok_or("missing cols")?.parse()?;
+        let size = args.next().map_or(Ok(10), |s| s.parse())?;
+        let monsters = args.next().map_or(Ok(0), |s| s.parse())?;
+        let seed = args.next().map_or(Ok(0), |s| s.parse())?;
+        let mut rng = StdRng::seed_from_u64(seed);
+        let puzzle = generate::puzzle(rows, cols, size, monsters, &mut rng)?;
+        write_tiles(&puzzle.tiles, File::create(&out)?)?;
+        // the answers the solver should give, next to the puzzle
+        let mut answers = File::create(format!("{}.answers", out))?;
+        writeln!(answers, "{}", puzzle.corner_product)?;
+        writeln!(answers, "{}", puzzle.roughness)?;
+        return Ok(());
+    }
+    let mut pattern = Pattern::sea_monster();
+    let mut show_matches = false;
+    let mut show_highlight = false;
+    let mut export_to = None;
+    let mut borders = false;
+    let mut ids = false;
+    let mut scale = 4;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "matches" => show_matches = true,
+            "highlight" => show_highlight = true,
+            // export FILE, as png, pbm or text by extension
+            "export" => export_to = Some(args.next().ok_or("missing export file")?),
+            "borders" => borders = true,
+            "ids" => ids = true,
+            "scale" => scale = args.next().ok_or("missing scale")?.parse()?,
+            _ => pattern = Pattern::read(&arg)?,
+        }
+    }
     let input = File::open(filename)?;
     let buffered = BufReader::new(input);
     let tiles: Vec<Tile> = read_tiles(buffered);
-    let tiles_copy = tiles.clone();
-    let top_edges: Vec<(ArrayView1<u8>, &Tile)> =
-        tiles.iter().map(|tile| (tile.data.row(0), tile)).collect();
-    let bottom_edges: Vec<(ArrayView1<u8>, &Tile)> = tiles
-        .iter()
-        .map(|tile| (tile.data.row(tile.data.nrows() - 1), tile))
-        .collect();
-    let left_edges: Vec<(ArrayView1<u8>, &Tile)> = tiles
-        .iter()
-        .map(|tile| (tile.data.column(0), tile))
-        .collect();
-    let right_edges: Vec<(ArrayView1<u8>, &Tile)> = tiles
-        .iter()
-        .map(|tile| (tile.data.column(tile.data.ncols() - 1), tile))
-        .collect();
-    let edge_index = EdgeIndex::new(top_edges, bottom_edges, left_edges, right_edges);
-    let mut corner = edge_index
-        .corners(&tiles)
-        .into_iter()
-        .next()
-        .unwrap()
-        .clone();
-    let owned_index: HashMap<Array1<u8>, Vec<Tile>> = edge_index
-        .edge_to_tile
-        .clone()
-        .into_iter()
-        .map(|(edge, tile)| (edge.into_owned(), tile.into_iter().cloned().collect()))
-        .collect();
-    orient_corner(&mut corner, &owned_index);
-    let left_edge = edge_index.build_strip(&tiles_copy, &Orientation::Bottom, corner);
-    let arranged_tiles: Vec<_> = left_edge
-        .into_iter()
-        .map(|tile| edge_index.build_strip(&tiles_copy, &Orientation::Right, tile))
-        .collect();
-    let mut final_image = Tile {
+    let assembly = assemble(&tiles).ok_or("tiles do not fit together")?;
+    dbg!(assembly.rows, assembly.cols);
+    dbg!(assembly.corner_ids().iter().product::<u64>());
+    // without assembling anything, as long as no two inner edges look alike
+    if let Ok(index) = EdgeIndex::new(&tiles) {
+        let corners: Vec<u64> = index.corners(&tiles).iter().map(|&i| tiles[i].id).collect();
+        dbg!(corners.iter().product::<u64>());
+    }
+    if let Some(out) = export_to {
+        let blocks = export::blocks(&assembly, borders);
+        let grid_ids = assembly.ids();
+        let labels = if ids { Some(&grid_ids[..]) } else { None };
+        let file = File::create(&out)?;
+        if out.ends_with(".png") {
+            export::write_png(&export::raster(&blocks, labels, scale), file)?;
+        } else if out.ends_with(".pbm") {
+            export::write_pbm(&export::raster(&blocks, labels, scale), file)?;
+        } else if ids {
+            BufWriter::new(file).write_all(export::overlay_text(&blocks, &grid_ids).as_bytes())?;
+        } else {
+            export::write_text(&assembly, borders, file)?;
+        }
+    }
+    let image = Tile {
         id: 0,
-        data: remove_borders_and_merge(arranged_tiles),
+        data: remove_borders_and_merge(assembly.grid()),
     };
-    //TODO: the rotations x flip permutations code again, i was lazy tho, so just did it manually
-    //here
-    final_image.flip_vertical();
-    final_image.rotate();
-    final_image.rotate();
-    final_image.rotate();
-    let monster_vec =
-        Vec::from("                  # #    ##    ##    ### #  #  #  #  #  #   ".as_bytes());
-    assert_eq!(monster_vec.len(), 60);
-    let hashes_in_monster = monster_vec.iter().filter(|b| b == &&b'#').count();
-    let sea_monster: Array2<u8> = Array2::from_shape_vec((3, 20), monster_vec).unwrap();
-    let monster_count = final_image
-        .data
-        .windows((3, 20))
-        .into_iter()
-        .filter(|window| {
-            sea_monster
-                .indexed_iter()
-                .filter(|(idx, data)| data == &&b'#' && window[*idx] == b'#')
-                .count()
-                == hashes_in_monster
-        })
-        .count();
-    let total_hashes = final_image.data.iter().filter(|b| b == &&b'#').count();
-    dbg!(total_hashes - monster_count * hashes_in_monster);
+    let matches = pattern.find(&image.data);
+    dbg!(matches.len());
+    if show_matches {
+        for m in &matches {
+            println!(
+                "{},{} flipped={} rotations={}",
+                m.row, m.col, m.transform.flipped, m.transform.rotations
+            );
+        }
+    }
+    // overlapping matches share cells, so count the cells rather than the matches
+    let covered = pattern.covered(&image.data, &matches);
+    dbg!(pattern::roughness(&image.data, &covered));
+    if show_highlight {
+        // turn the image so that most of the matches read the same way as the pattern
+        let mut counts: HashMap<Transform, usize> = HashMap::new();
+        for m in &matches {
+            *counts.entry(m.transform).or_default() += 1;
+        }
+        let upright = counts
+            .into_iter()
+            .max_by_key(|&(_, count)| count)
+            .map_or(Transform::IDENTITY, |(t, _)| t.inverse());
+        let highlighted = Tile {
+            id: 0,
+            data: pattern::highlight(&image.data, &covered),
+        };
+        print!(
+            "{}",
+            pattern::to_string(&highlighted.transformed(upright).data)
+        );
+    }
+
+    Ok(())
+}
 
+// assembles a random grid by grid puzzle with each kind of edge key
+fn bench(grid: usize, size: usize) -> Result<(), Box<dyn Error>> {
+    let tiles = generate::puzzle(grid, grid, size, 0, &mut StdRng::seed_from_u64(20))?.tiles;
+    let start = Instant::now();
+    let index = EdgeIndex::new(&tiles)?;
+    let corners = index.corners(&tiles);
+    println!(
+        "edge index and {} corners: {:?}",
+        corners.len(),
+        start.elapsed()
+    );
+    let start = Instant::now();
+    let by_bits = assemble_with::<u64>(&tiles).ok_or("tiles do not fit together")?;
+    println!("assembled with bitmask edges: {:?}", start.elapsed());
+    let start = Instant::now();
+    let by_cells = assemble_with::<Array1<u8>>(&tiles).ok_or("tiles do not fit together")?;
+    println!("assembled with array edges: {:?}", start.elapsed());
+    assert_eq!(by_bits.corner_ids(), by_cells.corner_ids());
     Ok(())
 }
 
-enum Corner {
-    TopLeft,
-    TopRight,
-    BottomLeft,
-    BottomRight,
+/// One of the eight symmetries of a square: a horizontal flip if `flipped`, followed by
+/// `rotations` quarter turns clockwise.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+struct Transform {
+    flipped: bool,
+    rotations: u8,
 }
 
-impl Corner {
-    fn opposite(&self) -> Self {
-        match self {
-            Corner::TopLeft => Corner::BottomRight,
-            Corner::TopRight => Corner::BottomLeft,
-            Corner::BottomLeft => Corner::TopRight,
-            Corner::BottomRight => Corner::TopLeft,
+impl Transform {
+    const IDENTITY: Transform = Transform {
+        flipped: false,
+        rotations: 0,
+    };
+
+    fn inverse(self) -> Transform {
+        // a flip followed by turns undoes itself, plain turns are undone by turning back
+        if self.flipped {
+            self
+        } else {
+            Transform {
+                flipped: false,
+                rotations: (4 - self.rotations) % 4,
+            }
         }
     }
+
+    fn all() -> impl Iterator<Item = Transform> {
+        (0..8).map(|i| Transform {
+            flipped: i >= 4,
+            rotations: i % 4,
+        })
+    }
 }
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 struct Tile {
     id: u64,
     data: Array2<u8>,
 }
 
-struct EdgeIndex<'a> {
-    edge_to_tile: HashMap<ArrayView1<'a, u8>, Vec<&'a Tile>>,
-}
 impl Tile {
     fn from_array(id: u64, data: Array2<u8>) -> Self {
         Tile { id, data }
     }
-    fn get_corner(&self, corner: &Corner) -> (ArrayView1<u8>, ArrayView1<u8>) {
-        match corner {
-            Corner::TopLeft => (self.top(), self.left()),
-            Corner::TopRight => (self.top(), self.right()),
-            Corner::BottomLeft => (self.bottom(), self.left()),
-            Corner::BottomRight => (self.bottom(), self.right()),
-        }
-    }
-    fn get_corner_owned(&self, corner: &Corner) -> (Array1<u8>, Array1<u8>) {
-        match corner {
-            Corner::TopLeft => (self.top().to_owned(), self.left().to_owned()),
-            Corner::TopRight => (self.top().to_owned(), self.right().to_owned()),
-            Corner::BottomLeft => (self.bottom().to_owned(), self.left().to_owned()),
-            Corner::BottomRight => (self.bottom().to_owned(), self.right().to_owned()),
-        }
-    }
-    fn top(&self) -> ArrayView1<u8> {
+    fn top(&self) -> ArrayView1<'_, u8> {
         self.data.row(0)
     }
-    fn bottom(&self) -> ArrayView1<u8> {
+    fn bottom(&self) -> ArrayView1<'_, u8> {
         self.data.row(self.data.nrows() - 1)
     }
-    fn left(&self) -> ArrayView1<u8> {
+    fn left(&self) -> ArrayView1<'_, u8> {
         self.data.column(0)
     }
-    fn right(&self) -> ArrayView1<u8> {
+    fn right(&self) -> ArrayView1<'_, u8> {
         self.data.column(self.data.ncols() - 1)
     }
-    fn edges(&self) -> Vec<ArrayView1<u8>> {
+    fn edges(&self) -> Vec<ArrayView1<'_, u8>> {
         vec![self.top(), self.bottom(), self.left(), self.right()]
     }
-    fn get_edge(&self, orientation: &Orientation) -> ArrayView1<u8> {
-        match orientation {
-            Orientation::Top => self.top(),
-            Orientation::Left => self.left(),
-            Orientation::Right => self.right(),
-            Orientation::Bottom => self.bottom(),
-        }
-    }
     // 90 degrees
     fn rotate(&mut self) {
         self.data.swap_axes(0, 1);
@@ -163,6 +226,19 @@ impl Tile {
             }
         }
     }
+    fn transform(&mut self, transform: Transform) {
+        if transform.flipped {
+            self.flip_horizontal();
+        }
+        for _ in 0..transform.rotations {
+            self.rotate();
+        }
+    }
+    fn transformed(&self, transform: Transform) -> Tile {
+        let mut tile = self.clone();
+        tile.transform(transform);
+        tile
+    }
     fn flip_vertical(&mut self) {
         for i in 0..(self.data.nrows() / 2) {
             for j in 0..self.data.ncols() {
@@ -172,7 +248,7 @@ impl Tile {
     }
 }
 
-fn read_tiles(mut buffered: BufReader<File>) -> Vec<Tile> {
+fn read_tiles<R: BufRead>(mut buffered: R) -> Vec<Tile> {
     let mut tiles = vec![];
     let mut data: Vec<u8> = Vec::new();
     let mut nrows = 0;
@@ -204,278 +280,34 @@ fn read_tiles(mut buffered: BufReader<File>) -> Vec<Tile> {
     }
     tiles
 }
-impl<'a> EdgeIndex<'a> {
-    fn new(
-        top: Vec<(ArrayView1<'a, u8>, &'a Tile)>,
-        bottom: Vec<(ArrayView1<'a, u8>, &'a Tile)>,
-        left: Vec<(ArrayView1<'a, u8>, &'a Tile)>,
-        right: Vec<(ArrayView1<'a, u8>, &'a Tile)>,
-    ) -> Self {
-        let mut edge_to_tile: HashMap<ArrayView1<'a, u8>, Vec<&'a Tile>> = HashMap::new();
-        let iter = top
-            .into_iter()
-            .chain(bottom.into_iter())
-            .chain(left.into_iter())
-            .chain(right.into_iter());
-        iter.for_each(|(edge, tile)| {
-            if let Some(v) = edge_to_tile.get_mut(&edge) {
-                v.push(tile);
-            } else {
-                edge_to_tile.insert(edge, vec![tile]);
-            }
-        });
-        Self { edge_to_tile }
-    }
-
-    fn corners(&self, tiles: &'a [Tile]) -> Vec<&'a Tile> {
-        let matches: Vec<&Tile> = tiles
-            .iter()
-            .filter(|tile| {
-                let mut unmatched_count = 0;
-                for corner in &[
-                    Corner::TopLeft,
-                    Corner::TopRight,
-                    Corner::BottomLeft,
-                    Corner::BottomRight,
-                ] {
-                    let (vertical_edge, horizontal_edge) = tile.get_corner(corner);
-                    let reversed_vertical_edge = vertical_edge.slice_move(s![..;-1]);
-                    let reversed_horizontal_edge = horizontal_edge.slice_move(s![..;-1]);
-
-                    if self
-                        .edge_to_tile
-                        .get(&vertical_edge)
-                        .map(Vec::len)
-                        .unwrap_or(0)
-                        + self
-                            .edge_to_tile
-                            .get(&reversed_vertical_edge)
-                            .map(Vec::len)
-                            .unwrap_or(0)
-                        == 1usize
-                        && self
-                            .edge_to_tile
-                            .get(&horizontal_edge)
-                            .map(Vec::len)
-                            .unwrap_or(0)
-                            + self
-                                .edge_to_tile
-                                .get(&reversed_horizontal_edge)
-                                .map(Vec::len)
-                                .unwrap_or(0)
-                            == 1usize
-                    {
-                        unmatched_count += 1
-                    }
-                }
-                unmatched_count == 1
-            })
-            .collect();
-        matches
-    }
-    fn build_strip(&self, _tiles: &Vec<Tile>, orientation: &Orientation, first: Tile) -> Vec<Tile> {
-        let mut strip: Vec<Tile> = vec![];
-        let mut used: HashSet<u64> = HashSet::new();
-        let owned_index: HashMap<Array1<u8>, Vec<Tile>> = self
-            .edge_to_tile
-            .clone()
-            .into_iter()
-            .map(|(edge, tile)| (edge.into_owned(), tile.into_iter().cloned().collect()))
-            .collect();
-        strip.push(first.clone());
-        used.insert(first.id);
-        let mut prev = first.clone();
-
-        while let Some(tile) = get_pair_and_orient(
-            &prev.get_edge(orientation).to_owned(),
-            &orientation.opposite(),
-            &owned_index,
-            &used,
-        ) {
-            prev = tile.clone();
-            used.insert(tile.id);
-            strip.push(tile);
-        }
-
-        strip
-    }
-}
 
-fn orient_corner(tile: &mut Tile, index: &HashMap<Array1<u8>, Vec<Tile>>) {
-    // rotate and flip tile such that the bottom right can be adjacent to a tile
-    let mut bottom = tile.bottom().to_owned();
-    //find bottom first
-    let mut c = 0;
-    while get_pair(tile.id, &bottom, index).is_none() && c < 4 {
-        tile.rotate();
-        c += 1;
-        bottom = tile.bottom().to_owned();
-    }
-    //didn't find a bottom, and 3 means we rotated back to where we were, so lets flip horizontally
-    if c >= 3 {
-        tile.flip_horizontal();
-        c = 0;
-        while get_pair(tile.id, &bottom, index).is_none() && c < 4 {
-            tile.rotate();
-            c += 1;
-            bottom = tile.bottom().to_owned();
-        }
-    }
-    //we want to build off the bottom right, so if the left has something, that means we need to
-    //rotate counterclockwise
-    if get_pair(tile.id, &tile.left().to_owned(), index).is_some() {
-        tile.rotate();
-        tile.rotate();
-        tile.rotate();
+fn write_tiles<W: Write>(tiles: &[Tile], out: W) -> io::Result<()> {
+    let mut out = BufWriter::new(out);
+    for tile in tiles {
+        writeln!(out, "Tile {}:", tile.id)?;
+        out.write_all(pattern::to_string(&tile.data).as_bytes())?;
+        writeln!(out)?;
     }
-    assert!(
-        get_pair(tile.id, &tile.bottom().to_owned(), index).is_some()
-            && get_pair(tile.id, &tile.right().to_owned(), index).is_some()
-    );
-}
-
-//an edge can be paired if there's some other tile with the same edge
-fn get_pair<'a>(
-    id: u64,
-    edge: &Array1<u8>,
-    index: &'a HashMap<Array1<u8>, Vec<Tile>>,
-) -> Option<&'a Tile> {
-    index
-        .get(&edge)
-        .filter(|v| v.iter().any(|t| t.id != id))
-        .map(|s| s.first().unwrap())
-}
-
-fn get_pair_and_orient<'a>(
-    edge: &Array1<u8>,
-    orientation: &Orientation,
-    index: &'a HashMap<Array1<u8>, Vec<Tile>>,
-    used: &HashSet<u64>,
-) -> Option<Tile> {
-    let mut tile = index
-        .get(&edge)
-        .map(|v| v.into_iter().filter(|t| !used.contains(&t.id)).next())
-        .flatten()
-        .cloned();
-    if let Some(ref mut t) = tile {
-        let mut c = 0;
-        while &t.get_edge(orientation).to_owned() != edge && c < 4 {
-            c += 1;
-            t.rotate();
-        }
-        if &t.get_edge(orientation).to_owned() != edge {
-            c = 0;
-            t.flip_horizontal();
-            while &t.get_edge(orientation).to_owned() != edge && c < 4 {
-                c += 1;
-                t.rotate();
-            }
-        }
-        if &t.get_edge(orientation).to_owned() != edge {
-            c = 0;
-            t.flip_horizontal();
-            t.flip_vertical();
-            while &t.get_edge(orientation).to_owned() != edge && c < 4 {
-                c += 1;
-                t.rotate();
-            }
-        }
-        if &t.get_edge(orientation).to_owned() != edge {
-            c = 0;
-            t.flip_horizontal();
-            while &t.get_edge(orientation).to_owned() != edge && c < 4 {
-                c += 1;
-                t.rotate();
-            }
-        }
-        assert_eq!(&t.get_edge(orientation).to_owned(), edge);
-    }
-    if tile.is_some() {
-        return tile;
-    }
-    let edge = &edge.slice(s![..;-1]).to_owned();
-    let mut tile = index
-        .get(&edge)
-        .map(|v| v.into_iter().filter(|t| !used.contains(&t.id)).next())
-        .flatten()
-        .cloned();
-    if let Some(ref mut t) = tile {
-        let mut c = 0;
-        while &t.get_edge(orientation).to_owned() != edge && c < 4 {
-            c += 1;
-            t.rotate();
-        }
-        if &t.get_edge(orientation).to_owned() != edge {
-            c = 0;
-            t.flip_horizontal();
-            while &t.get_edge(orientation).to_owned() != edge && c < 4 {
-                c += 1;
-                t.rotate();
-            }
-        }
-        if &t.get_edge(orientation).to_owned() != edge {
-            c = 0;
-            t.flip_horizontal();
-            t.flip_vertical();
-            while &t.get_edge(orientation).to_owned() != edge && c < 4 {
-                c += 1;
-                t.rotate();
-            }
-        }
-        if &t.get_edge(orientation).to_owned() != edge {
-            c = 0;
-            t.flip_horizontal();
-            while &t.get_edge(orientation).to_owned() != edge && c < 4 {
-                c += 1;
-                t.rotate();
-            }
-        }
-        assert_eq!(&t.get_edge(orientation).to_owned(), edge);
-    }
-    tile.map(|mut t| {
-        match orientation {
-            Orientation::Top | Orientation::Bottom => t.flip_horizontal(),
-            Orientation::Left | Orientation::Right => t.flip_vertical(),
-        }
-        t
-    })
+    Ok(())
 }
-
 fn remove_borders_and_merge(tiles: Vec<Vec<Tile>>) -> Array2<u8> {
-    let mut image = Array2::zeros((8 * 12, 8 * 12));
-    for i in 0..tiles.len() {
-        for j in 0..tiles[0].len() {
-            let borderless = tiles[i][j].data.slice(s![1..9, 1..9]);
-            for r in 0..borderless.nrows() {
-                for c in 0..borderless.ncols() {
-                    let dest_r = i * 8 + r;
-                    let dest_c = j * 8 + c;
-                    image[[dest_r, dest_c]] = borderless[[r, c]];
-                }
-            }
+    let (nrows, ncols) = tiles[0][0].data.dim();
+    let (inner_rows, inner_cols) = (nrows - 2, ncols - 2);
+    let mut image = Array2::zeros((inner_rows * tiles.len(), inner_cols * tiles[0].len()));
+    for (i, row) in tiles.iter().enumerate() {
+        for (j, tile) in row.iter().enumerate() {
+            let borderless = tile.data.slice(s![1..nrows - 1, 1..ncols - 1]);
+            image
+                .slice_mut(s![
+                    i * inner_rows..(i + 1) * inner_rows,
+                    j * inner_cols..(j + 1) * inner_cols
+                ])
+                .assign(&borderless);
         }
     }
     image
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
-enum Orientation {
-    Top,
-    Left,
-    Bottom,
-    Right,
-}
-impl Orientation {
-    fn opposite(&self) -> Self {
-        match self {
-            Orientation::Top => Orientation::Bottom,
-            Orientation::Left => Orientation::Right,
-            Orientation::Bottom => Orientation::Top,
-            Orientation::Right => Orientation::Left,
-        }
-    }
-}
-
 #[test]
 fn rotate() {
     use ndarray::arr2;
@@ -517,3 +349,145 @@ fn flip_horizontal() {
     let expected = arr2(&[[2, 1], [4, 3]]);
     assert_eq!(tile.data, expected);
 }
+
+#[test]
+fn transforms_are_distinct() {
+    use ndarray::arr2;
+
+    let tile = Tile {
+        id: 123,
+        data: arr2(&[[1, 2, 3], [4, 5, 6], [7, 8, 9]]),
+    };
+    let images: std::collections::HashSet<Array2<u8>> = Transform::all()
+        .map(|t| tile.transformed(t).data.as_standard_layout().to_owned())
+        .collect();
+    assert_eq!(images.len(), 8);
+    assert_eq!(tile.transformed(Transform::IDENTITY), tile);
+}
+
+#[test]
+fn assemble_rectangle() {
+    // a 2x3 grid of 8x8 tiles cut from one picture, neighbours sharing their edges
+    let mut seed = 7u32;
+    let picture = Array2::from_shape_fn((15, 22), |_| {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        if (seed >> 16) & 1 == 0 {
+            b'#'
+        } else {
+            b'.'
+        }
+    });
+    let tiles: Vec<Tile> = (0..6)
+        .map(|i| {
+            let (row, col) = (i / 3 * 7, i % 3 * 7);
+            let data = picture.slice(s![row..row + 8, col..col + 8]).to_owned();
+            let transform = Transform::all().nth(i * 5 % 8).unwrap();
+            Tile::from_array(i as u64, data).transformed(transform)
+        })
+        .rev()
+        .collect();
+    let assembly = assemble(&tiles).unwrap();
+    assert_eq!(assembly.rows * assembly.cols, 6);
+    for row in 0..assembly.rows {
+        for col in 0..assembly.cols {
+            let tile = assembly.get(row, col);
+            if col > 0 {
+                assert_eq!(assembly.get(row, col - 1).right(), tile.left());
+            }
+            if row > 0 {
+                assert_eq!(assembly.get(row - 1, col).bottom(), tile.top());
+            }
+        }
+    }
+    let mut corners = assembly.corner_ids();
+    corners.sort_unstable();
+    assert_eq!(corners, [0, 2, 3, 5]);
+}
+
+#[test]
+fn overlapping_matches() {
+    use ndarray::arr2;
+
+    let image = arr2(&[[b'#', b'#', b'#'], [b'.', b'.', b'#']]);
+    let pattern = Pattern::parse("##").unwrap();
+    let matches = pattern.find(&image);
+    // two across the top, one down the right, and the flipped and turned copies of "##"
+    // are all the same shape
+    assert_eq!(matches.len(), 3);
+    let covered = pattern.covered(&image, &matches);
+    assert_eq!(covered.iter().filter(|&&c| c).count(), 4);
+    assert_eq!(
+        pattern::to_string(&pattern::highlight(&image, &covered)),
+        "OOO\n..O\n"
+    );
+}
+
+#[test]
+fn edge_bits_follow_transforms() {
+    let mut rng = StdRng::seed_from_u64(1);
+    let tile = Tile::from_array(1, generate::random_image(10, 10, &mut rng));
+    for (t, sides) in Transform::all().zip(edges::sides(&tile).iter()) {
+        let turned = tile.transformed(t);
+        let expected = [turned.top(), turned.right(), turned.bottom(), turned.left()];
+        assert_eq!(
+            sides.to_vec(),
+            expected.iter().map(|&e| edges::bits(e)).collect::<Vec<_>>()
+        );
+    }
+}
+
+#[test]
+fn solves_generated_puzzles() {
+    let mut rng = StdRng::seed_from_u64(48);
+    // the last one is too wide for bitmask edges
+    for &(rows, cols, size, monsters) in &[
+        (3, 3, 10, 1),
+        (4, 2, 12, 2),
+        (12, 12, 10, 30),
+        (2, 2, 70, 1),
+    ] {
+        let puzzle = generate::puzzle(rows, cols, size, monsters, &mut rng).unwrap();
+        let mut text = vec![];
+        write_tiles(&puzzle.tiles, &mut text).unwrap();
+        let tiles = read_tiles(&text[..]);
+        assert_eq!(tiles, puzzle.tiles);
+        let assembly = assemble(&tiles).unwrap();
+        assert_eq!(
+            assembly.corner_ids().iter().product::<u64>(),
+            puzzle.corner_product
+        );
+        let image = remove_borders_and_merge(assembly.grid());
+        assert!(Transform::all().any(|t| {
+            let original = Tile {
+                id: 0,
+                data: puzzle.image.clone(),
+            };
+            original.transformed(t).data == image
+        }));
+        let monster = Pattern::sea_monster();
+        let covered = monster.covered(&image, &monster.find(&image));
+        assert_eq!(pattern::roughness(&image, &covered), puzzle.roughness);
+    }
+}
+
+#[test]
+fn export_round_trip() {
+    let mut rng = StdRng::seed_from_u64(49);
+    let puzzle = generate::puzzle(2, 3, 6, 0, &mut rng).unwrap();
+    let assembly = assemble(&puzzle.tiles).unwrap();
+    let mut text = vec![];
+    export::write_text(&assembly, true, &mut text).unwrap();
+    assert_eq!(read_tiles(&text[..]), assembly.tiles);
+    let mut text = vec![];
+    export::write_text(&assembly, false, &mut text).unwrap();
+    let image = remove_borders_and_merge(assembly.grid());
+    assert_eq!(read_tiles(&text[..])[0].data, image);
+    let blocks = export::blocks(&assembly, false);
+    assert_eq!(export::merge(&blocks), image);
+    // a label strip and a grid line above each row of 4x4 cell blocks, and one below
+    let canvas = export::raster(&blocks, Some(&assembly.ids()), 2);
+    assert_eq!(canvas.dim(), (2 * (7 + 8 + 1) + 1, 3 * (8 + 1) + 1));
+    let overlay = export::overlay_text(&blocks, &assembly.ids());
+    assert_eq!(overlay.lines().count(), 2 * (1 + 4) + 1);
+    assert!(overlay.starts_with(&format!("+{}", assembly.tiles[0].id)));
+}
@@ -0,0 +1,135 @@
+use crate::{Tile, Transform};
+use ndarray::Array2;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+
+pub const SEA_MONSTER: &str = "                  # \n#    ##    ##    ###\n #  #  #  #  #  #   ";
+
+/// An ASCII picture where every `#` must line up with a `#` in the image and anything
+/// else is ignored.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    pub data: Array2<u8>,
+}
+
+/// A pattern found in the image, with `transform` applied to the pattern and its top left
+/// corner at `row`, `col`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match {
+    pub row: usize,
+    pub col: usize,
+    pub transform: Transform,
+}
+
+impl Pattern {
+    pub fn parse(s: &str) -> Result<Self, Box<dyn Error>> {
+        let lines: Vec<&str> = s.lines().collect();
+        let width = lines.iter().map(|line| line.len()).max().unwrap_or(0);
+        if width == 0 {
+            return Err("empty pattern".into());
+        }
+        let mut data = Array2::from_elem((lines.len(), width), b' ');
+        for (r, line) in lines.iter().enumerate() {
+            for (c, b) in line.bytes().enumerate() {
+                data[[r, c]] = b;
+            }
+        }
+        Ok(Pattern { data })
+    }
+
+    pub fn read(filename: &str) -> Result<Self, Box<dyn Error>> {
+        Self::parse(&fs::read_to_string(filename)?)
+    }
+
+    pub fn sea_monster() -> Self {
+        Self::parse(SEA_MONSTER).unwrap()
+    }
+
+    fn cells(data: &Array2<u8>) -> Vec<(usize, usize)> {
+        data.indexed_iter()
+            .filter(|(_, &b)| b == b'#')
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    /// Every placement of the pattern in any of its eight orientations, including ones that
+    /// overlap each other. Orientations that look the same as an earlier one are skipped so
+    /// symmetric patterns are only reported once.
+    pub fn find(&self, image: &Array2<u8>) -> Vec<Match> {
+        let tile = Tile {
+            id: 0,
+            data: self.data.clone(),
+        };
+        let mut seen = HashSet::new();
+        let mut matches = vec![];
+        for transform in Transform::all() {
+            let oriented = tile.transformed(transform).data;
+            let cells = Self::cells(&oriented);
+            if !seen.insert(cells.clone()) {
+                continue;
+            }
+            let (nrows, ncols) = oriented.dim();
+            if nrows > image.nrows() || ncols > image.ncols() {
+                continue;
+            }
+            for row in 0..=image.nrows() - nrows {
+                for col in 0..=image.ncols() - ncols {
+                    if cells
+                        .iter()
+                        .all(|&(r, c)| image[[row + r, col + c]] == b'#')
+                    {
+                        matches.push(Match {
+                            row,
+                            col,
+                            transform,
+                        });
+                    }
+                }
+            }
+        }
+        matches
+    }
+
+    /// The image cells covered by at least one match.
+    pub fn covered(&self, image: &Array2<u8>, matches: &[Match]) -> Array2<bool> {
+        let tile = Tile {
+            id: 0,
+            data: self.data.clone(),
+        };
+        let mut covered = Array2::from_elem(image.dim(), false);
+        for m in matches {
+            for (r, c) in Self::cells(&tile.transformed(m.transform).data) {
+                covered[[m.row + r, m.col + c]] = true;
+            }
+        }
+        covered
+    }
+}
+
+/// How many `#` cells are not part of any match.
+pub fn roughness(image: &Array2<u8>, covered: &Array2<bool>) -> usize {
+    image
+        .iter()
+        .zip(covered.iter())
+        .filter(|&(&b, &c)| b == b'#' && !c)
+        .count()
+}
+
+/// The image with every cell of every match drawn as `O`.
+pub fn highlight(image: &Array2<u8>, covered: &Array2<bool>) -> Array2<u8> {
+    let mut out = image.clone();
+    out.zip_mut_with(covered, |b, &c| {
+        if c {
+            *b = b'O'
+        }
+    });
+    out
+}
+
+pub fn to_string(image: &Array2<u8>) -> String {
+    image
+        .outer_iter()
+        .map(|row| row.iter().map(|&b| b as char).collect::<String>() + "\n")
+        .collect()
+}
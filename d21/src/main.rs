@@ -1,88 +1,131 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashSet};
 use std::error::Error;
+use std::fmt;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
+mod solver;
+use solver::Mapping;
+
 fn main() -> Result<(), Box<dyn Error>> {
-    let filename = std::env::args().nth(1).unwrap();
+    let mut args = std::env::args().skip(1);
+    let filename = args.next().unwrap();
+    let mut show_safe = false;
+    let mut limit = 100;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "safe" => show_safe = true,
+            // limit N: most mappings to list when ambiguous
+            "limit" => limit = args.next().ok_or("missing limit")?.parse()?,
+            _ => return Err(format!("unknown argument {:?}", arg).into()),
+        }
+    }
     let input = File::open(filename)?;
     let buffered = BufReader::new(input);
     let facts: Vec<Statement> = buffered
         .lines()
         .map(|l| Statement::new(&l.unwrap()))
         .collect();
-    let all_allergens: HashSet<String> = facts.iter().fold(HashSet::new(), |acc, fact| {
-        acc.union(&fact.allergens).cloned().collect()
-    });
-    let mut allergens_to_ingredients: HashMap<String, HashSet<String>> = all_allergens
+    let all_allergens: BTreeSet<&String> = facts.iter().flat_map(|f| &f.allergens).collect();
+    let all_ingredients: BTreeSet<&String> = facts.iter().flat_map(|f| &f.ingredients).collect();
+    let allergens: Vec<&String> = all_allergens.into_iter().collect();
+    let ingredients: Vec<&String> = all_ingredients.into_iter().collect();
+    // an allergen can only be in ingredients listed by every food that contains it
+    let candidates: Vec<Vec<usize>> = allergens
         .iter()
-        .map(|allergen| {
-            (
-                allergen.clone(),
-                facts
-                    .iter()
-                    .filter_map(|fact| {
-                        if fact.allergens.contains(allergen) {
-                            Some(fact.ingredients.clone())
-                        } else {
-                            None
-                        }
-                    })
-                    .fold(None, |acc, set| match acc {
-                        None => Some(set),
-                        Some(collected) => Some(collected.intersection(&set).cloned().collect()),
-                    }),
-            )
-        })
-        .map(|(allergen, o)| {
-            (
-                allergen,
-                match o {
-                    None => HashSet::new(),
-                    Some(set) => set,
-                },
-            )
-        })
-        .collect();
-    let mut canonical_dangerous_ingredients: Vec<(String, String)> = vec![];
-    while !allergens_to_ingredients.is_empty() {
-        let known_ingredients: HashMap<String, String> = allergens_to_ingredients
-            .iter()
-            .filter_map(|(k, v)| {
-                if v.len() == 1 {
-                    Some((v.iter().next().unwrap().clone(), k.clone()))
-                } else {
-                    None
-                }
-            })
-            .collect();
-        for (k, v) in known_ingredients {
-            canonical_dangerous_ingredients.push((k.clone(), v));
-            allergens_to_ingredients.iter_mut().for_each(|(_, v)| {
-                v.remove(&k);
-            });
-            allergens_to_ingredients = allergens_to_ingredients
-                .iter()
-                .filter_map(|(k, v)| {
-                    if v.is_empty() {
-                        None
-                    } else {
-                        Some((k.clone(), v.clone()))
-                    }
+        .map(|&allergen| {
+            (0..ingredients.len())
+                .filter(|&i| {
+                    facts
+                        .iter()
+                        .filter(|fact| fact.allergens.contains(allergen))
+                        .all(|fact| fact.ingredients.contains(ingredients[i]))
                 })
                 .collect()
-        }
+        })
+        .collect();
+
+    let mapping = solver::solve(&candidates, ingredients.len(), limit);
+    if let Mapping::Unsatisfiable {
+        allergens: stuck,
+        ingredients: between,
+    } = mapping
+    {
+        let stuck: Vec<&String> = stuck.iter().map(|&a| allergens[a]).collect();
+        let between: Vec<&String> = between.iter().map(|&i| ingredients[i]).collect();
+        return Err(Box::new(StrError(format!(
+            "allergens {:?} can only be in {:?}",
+            stuck, between
+        ))));
     }
-    canonical_dangerous_ingredients.sort_by_key(|t| t.1.clone());
-    let sorted_vec: Vec<String> = canonical_dangerous_ingredients
+    let possible = solver::possible(&candidates, ingredients.len());
+    let mut safe: Vec<(&String, usize)> = ingredients
         .iter()
-        .map(|t| t.0.clone())
+        .enumerate()
+        .filter(|(i, _)| !possible.iter().any(|p| p.contains(i)))
+        .map(|(_, &ingredient)| {
+            let count = facts
+                .iter()
+                .filter(|fact| fact.ingredients.contains(ingredient))
+                .count();
+            (ingredient, count)
+        })
         .collect();
-    dbg!(sorted_vec.join(","));
+    safe.sort();
+    if show_safe {
+        for (ingredient, count) in &safe {
+            println!("{} {}", ingredient, count);
+        }
+    }
+    dbg!(safe.len());
+    dbg!(safe.iter().map(|(_, count)| count).sum::<usize>());
+
+    match mapping {
+        Mapping::Unique(mapping) => {
+            for (allergen, &ingredient) in allergens.iter().zip(&mapping) {
+                println!("{}: {}", allergen, ingredients[ingredient]);
+            }
+            // allergens are sorted already
+            let dangerous: Vec<&str> = mapping.iter().map(|&i| ingredients[i].as_str()).collect();
+            dbg!(dangerous.join(","));
+        }
+        Mapping::Unsatisfiable { .. } => unreachable!(),
+        Mapping::Ambiguous {
+            mappings,
+            allergens: undecided,
+        } => {
+            let undecided: Vec<&String> = undecided.iter().map(|&a| allergens[a]).collect();
+            let more = if mappings.len() == limit {
+                " or more"
+            } else {
+                ""
+            };
+            println!(
+                "{}{} mappings, undecided between {:?}",
+                mappings.len(),
+                more,
+                undecided
+            );
+            for mapping in mappings {
+                let dangerous: Vec<&str> =
+                    mapping.iter().map(|&i| ingredients[i].as_str()).collect();
+                println!("{}", dangerous.join(","));
+            }
+        }
+    }
 
     Ok(())
 }
 
+#[derive(Debug, Clone)]
+struct StrError(String);
+impl Error for StrError {}
+impl fmt::Display for StrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 #[derive(Debug)]
 struct Statement {
     ingredients: HashSet<String>,
@@ -90,11 +133,13 @@ struct Statement {
 }
 impl Statement {
     fn new(s: &str) -> Self {
-        let mut iter = s[..s.len() - 1].split(" (contains ");
+        // foods don't have to list any allergens
+        let mut iter = s.trim_end_matches(')').split(" (contains ");
         let ingredients = iter.next().unwrap();
-        let allergens = iter.next().unwrap();
         let ingredients = ingredients.split_whitespace().map(String::from).collect();
-        let allergens = allergens.split(", ").map(String::from).collect();
+        let allergens = iter
+            .next()
+            .map_or_else(HashSet::new, |a| a.split(", ").map(String::from).collect());
         Statement {
             ingredients,
             allergens,
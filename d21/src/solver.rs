@@ -0,0 +1,214 @@
+/// Outcome of matching allergens to the ingredients that contain them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mapping {
+    /// `ingredients[allergen]` is the only possible ingredient for every allergen.
+    Unique(Vec<usize>),
+    /// More than one mapping fits, `allergens` are the ones that differ between them.
+    Ambiguous {
+        mappings: Vec<Vec<usize>>,
+        allergens: Vec<usize>,
+    },
+    /// `allergens` can only be in the fewer `ingredients` between them, so no mapping exists.
+    Unsatisfiable {
+        allergens: Vec<usize>,
+        ingredients: Vec<usize>,
+    },
+}
+
+/// Puts every allergen in a distinct ingredient, `candidates[allergen]` being the
+/// ingredients it could be in. At most `limit` mappings are collected when ambiguous.
+pub fn solve(candidates: &[Vec<usize>], num_ingredients: usize, limit: usize) -> Mapping {
+    let matching = max_matching(candidates, num_ingredients, &vec![false; num_ingredients]);
+    if let Some(unmatched) = matching.iter().position(Option::is_none) {
+        let (allergens, ingredients) = hall_violator(candidates, &matching, unmatched);
+        return Mapping::Unsatisfiable {
+            allergens,
+            ingredients,
+        };
+    }
+    let candidates = propagate(candidates);
+    let mut mappings = vec![];
+    let mut partial = vec![None; candidates.len()];
+    let mut used = vec![false; num_ingredients];
+    enumerate(&candidates, &mut partial, &mut used, limit, &mut mappings);
+    if mappings.len() == 1 {
+        return Mapping::Unique(mappings.pop().unwrap());
+    }
+    let allergens = (0..candidates.len())
+        .filter(|&i| mappings.iter().any(|m| m[i] != mappings[0][i]))
+        .collect();
+    Mapping::Ambiguous {
+        mappings,
+        allergens,
+    }
+}
+
+/// For each allergen, the ingredients it is in under at least one complete mapping. Any
+/// ingredient missing from all of these is provably free of allergens.
+pub fn possible(candidates: &[Vec<usize>], num_ingredients: usize) -> Vec<Vec<usize>> {
+    let candidates = propagate(candidates);
+    (0..candidates.len())
+        .map(|allergen| {
+            candidates[allergen]
+                .iter()
+                .copied()
+                .filter(|&ingredient| {
+                    // with the allergen pinned there, the others still have to fit
+                    let mut taken = vec![false; num_ingredients];
+                    taken[ingredient] = true;
+                    let mut rest = candidates.clone();
+                    rest[allergen].clear();
+                    max_matching(&rest, num_ingredients, &taken)
+                        .iter()
+                        .enumerate()
+                        .all(|(i, m)| i == allergen || m.is_some())
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// An allergen with a single candidate ingredient rules that ingredient out for every other
+// allergen, which can leave another with a single candidate, and so on.
+fn propagate(candidates: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let mut candidates = candidates.to_vec();
+    let mut settled = vec![false; candidates.len()];
+    while let Some(allergen) =
+        (0..candidates.len()).find(|&i| !settled[i] && candidates[i].len() == 1)
+    {
+        settled[allergen] = true;
+        let ingredient = candidates[allergen][0];
+        for (other, c) in candidates.iter_mut().enumerate() {
+            if other != allergen {
+                c.retain(|&i| i != ingredient);
+            }
+        }
+    }
+    candidates
+}
+
+// From here down is d16's matcher, reused as is with positions and fields renamed to
+// allergens and ingredients, so a fix to one belongs in the other.
+
+// Kuhn's augmenting paths, `matching[allergen]` is the ingredient it got
+fn max_matching(
+    candidates: &[Vec<usize>],
+    num_ingredients: usize,
+    taken: &[bool],
+) -> Vec<Option<usize>> {
+    let mut owner: Vec<Option<usize>> = vec![None; num_ingredients];
+    for allergen in 0..candidates.len() {
+        let mut seen = taken.to_vec();
+        augment(candidates, allergen, &mut owner, &mut seen);
+    }
+    let mut matching = vec![None; candidates.len()];
+    for (ingredient, allergen) in owner.into_iter().enumerate() {
+        if let Some(allergen) = allergen {
+            matching[allergen] = Some(ingredient);
+        }
+    }
+    matching
+}
+
+fn augment(
+    candidates: &[Vec<usize>],
+    allergen: usize,
+    owner: &mut [Option<usize>],
+    seen: &mut [bool],
+) -> bool {
+    for &ingredient in &candidates[allergen] {
+        if seen[ingredient] {
+            continue;
+        }
+        seen[ingredient] = true;
+        let free = match owner[ingredient] {
+            None => true,
+            Some(other) => augment(candidates, other, owner, seen),
+        };
+        if free {
+            owner[ingredient] = Some(allergen);
+            return true;
+        }
+    }
+    false
+}
+
+// Allergens reachable from an unmatched one by alternating paths have fewer candidate
+// ingredients between them than there are allergens (Hall's theorem).
+fn hall_violator(
+    candidates: &[Vec<usize>],
+    matching: &[Option<usize>],
+    unmatched: usize,
+) -> (Vec<usize>, Vec<usize>) {
+    let mut allergens = vec![unmatched];
+    let mut ingredients: Vec<usize> = vec![];
+    let mut stack = vec![unmatched];
+    while let Some(allergen) = stack.pop() {
+        for &ingredient in &candidates[allergen] {
+            if ingredients.contains(&ingredient) {
+                continue;
+            }
+            ingredients.push(ingredient);
+            if let Some(other) = matching.iter().position(|&m| m == Some(ingredient)) {
+                if !allergens.contains(&other) {
+                    allergens.push(other);
+                    stack.push(other);
+                }
+            }
+        }
+    }
+    allergens.sort_unstable();
+    ingredients.sort_unstable();
+    (allergens, ingredients)
+}
+
+// Backtracking over the most constrained allergen first, pruned by checking a complete
+// matching of the rest still exists.
+fn enumerate(
+    candidates: &[Vec<usize>],
+    partial: &mut [Option<usize>],
+    used: &mut [bool],
+    limit: usize,
+    mappings: &mut Vec<Vec<usize>>,
+) {
+    if mappings.len() >= limit {
+        return;
+    }
+    let next = (0..candidates.len())
+        .filter(|&i| partial[i].is_none())
+        .min_by_key(|&i| candidates[i].iter().filter(|&&f| !used[f]).count());
+    let allergen = match next {
+        Some(allergen) => allergen,
+        None => {
+            mappings.push(partial.iter().map(|f| f.unwrap()).collect());
+            return;
+        }
+    };
+    for &ingredient in &candidates[allergen] {
+        if used[ingredient] {
+            continue;
+        }
+        partial[allergen] = Some(ingredient);
+        used[ingredient] = true;
+        let remaining: Vec<Vec<usize>> = candidates
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                if partial[i].is_some() {
+                    vec![]
+                } else {
+                    c.clone()
+                }
+            })
+            .collect();
+        let feasible = max_matching(&remaining, used.len(), used)
+            .iter()
+            .enumerate()
+            .all(|(i, m)| partial[i].is_some() || m.is_some());
+        if feasible {
+            enumerate(candidates, partial, used, limit, mappings);
+        }
+        partial[allergen] = None;
+        used[ingredient] = false;
+    }
+}